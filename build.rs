@@ -0,0 +1,18 @@
+fn main() {
+    #[cfg(feature = "ffi")]
+    generate_header();
+}
+
+/// Writes the header of the C API to `$OUT_DIR/cwparse.h`, which the tests
+/// compare against the checked-in `include/cwparse.h`.
+#[cfg(feature = "ffi")]
+fn generate_header() {
+    let dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    cbindgen::generate(dir)
+        .expect("failed to generate the C header")
+        .write_to_file(format!("{out_dir}/cwparse.h"));
+}
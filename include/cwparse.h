@@ -0,0 +1,153 @@
+#ifndef CWPARSE_H
+#define CWPARSE_H
+
+/* Generated by cbindgen from src/ffi.rs. Do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * A parsed map, which must be freed with `cw_map_free`.
+ */
+typedef struct CwMap CwMap;
+
+/**
+ * A row of a section layout.
+ */
+typedef struct CwSymbol {
+  /**
+   * The address of the symbol relative to its section.
+   */
+  uint32_t addr;
+  uint32_t virt_addr;
+  /**
+   * Only valid if `has_file_addr` is set.
+   */
+  uint32_t file_addr;
+  bool has_file_addr;
+  /**
+   * Zero for entry symbols, which are part of their parent symbol.
+   */
+  uint32_t size;
+  uint8_t align;
+  const char *name;
+  /**
+   * The symbol that an entry symbol is part of, or null.
+   */
+  const char *parent;
+  const char *section;
+  const char *object;
+  /**
+   * The source file within the object, or null.
+   */
+  const char *source;
+} CwSymbol;
+
+/**
+ * A row of the memory map.
+ */
+typedef struct CwMemoryEntry {
+  const char *name;
+  /**
+   * Only valid if `has_virt_addr` is set, which it is not for debug
+   * sections.
+   */
+  uint32_t virt_addr;
+  bool has_virt_addr;
+  uint32_t size;
+  uint32_t file_addr;
+  /**
+   * The ROM columns are only valid if `has_rom` is set.
+   */
+  bool has_rom;
+  uint32_t rom_addr;
+  uint32_t ram_buffer_addr;
+  uint32_t s_record_line;
+  uint32_t bin_file_addr;
+  const char *bin_file_name;
+} CwMemoryEntry;
+
+/**
+ * A row of the linker generated symbols table.
+ */
+typedef struct CwLinkerEntry {
+  const char *name;
+  uint32_t virt_addr;
+} CwLinkerEntry;
+
+/**
+ * The message of the last error on this thread, or null. It stays valid
+ * until the next call that fails.
+ */
+const char *cw_last_error(void);
+
+/**
+ * Parses the `len` bytes of UTF-8 at `text`, or returns null on failure,
+ * including when `text` is null.
+ *
+ * # Safety
+ *
+ * `text` must be null or point to `len` readable bytes.
+ */
+struct CwMap *cw_map_parse(const char *text, size_t len);
+
+/**
+ * Reads and parses the map at `path`, or returns null on failure,
+ * including when `path` is null.
+ *
+ * # Safety
+ *
+ * `path` must be null or a NUL-terminated string.
+ */
+struct CwMap *cw_map_open(const char *path);
+
+/**
+ * Frees a map returned by `cw_map_parse` or `cw_map_open`.
+ *
+ * # Safety
+ *
+ * `map` must be null or a map that has not been freed yet.
+ */
+void cw_map_free(struct CwMap *map);
+
+/**
+ * The rows of every section layout, storing their number in `len`.
+ *
+ * # Safety
+ *
+ * `map` must be a valid map and `len` must be writable.
+ */
+const struct CwSymbol *cw_map_symbols(const struct CwMap *map, size_t *len);
+
+/**
+ * The rows of the memory map, storing their number in `len`.
+ *
+ * # Safety
+ *
+ * `map` must be a valid map and `len` must be writable.
+ */
+const struct CwMemoryEntry *cw_map_memory_entries(const struct CwMap *map, size_t *len);
+
+/**
+ * The rows of the linker generated symbols table, storing their number in
+ * `len`.
+ *
+ * # Safety
+ *
+ * `map` must be a valid map and `len` must be writable.
+ */
+const struct CwLinkerEntry *cw_map_linker_entries(const struct CwMap *map, size_t *len);
+
+/**
+ * Finds the symbol whose data contains `virt_addr`, or returns null.
+ *
+ * # Safety
+ *
+ * `map` must be a valid map.
+ */
+const struct CwSymbol *cw_map_lookup(const struct CwMap *map, uint32_t virt_addr);
+
+#endif  /* CWPARSE_H */
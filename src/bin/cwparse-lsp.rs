@@ -0,0 +1,735 @@
+//! A language server for CodeWarrior map files, speaking the Language Server
+//! Protocol over stdio.
+
+use cwparse::{
+    demangle::demangle,
+    document::{Document, Edit},
+    error::Error,
+    map::{Identifier, Line, Origin, SectionName},
+    section_table::{self, Symbol},
+    span::{FieldKind, LocatedLine, Span},
+    tree::{self, Node, Specifier},
+};
+use lsp_server::{
+    Connection, ErrorCode, Message, Notification, Request, Response,
+};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+        Notification as _, PublishDiagnostics,
+    },
+    request::{
+        DocumentSymbolRequest, GotoDefinition, HoverRequest, Request as _,
+    },
+    Diagnostic, DiagnosticSeverity, DocumentSymbol, DocumentSymbolParams,
+    DocumentSymbolResponse, GotoDefinitionParams, GotoDefinitionResponse,
+    Hover, HoverContents, HoverParams, HoverProviderCapability, Location,
+    MarkupContent, MarkupKind, OneOf, Position, PublishDiagnosticsParams,
+    Range, ServerCapabilities, SymbolKind, TextDocumentSyncCapability,
+    TextDocumentSyncKind, Uri,
+};
+use std::{cell::OnceCell, collections::HashMap};
+
+type Result<T> =
+    std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
+
+fn main() -> Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = serde_json::to_value(ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(
+            TextDocumentSyncKind::INCREMENTAL,
+        )),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        ..Default::default()
+    })?;
+    connection.initialize(capabilities)?;
+
+    Server {
+        connection,
+        documents: HashMap::new(),
+    }
+    .run()?;
+    io_threads.join()?;
+
+    Ok(())
+}
+
+struct Server {
+    connection: Connection,
+    documents: HashMap<Uri, Open>,
+}
+
+/// An open document, along with the index of its section layout rows for
+/// the current version.
+struct Open {
+    document: Document,
+    /// Built by the first request after each change.
+    rows: OnceCell<Rows>,
+}
+
+impl Open {
+    fn new(document: Document) -> Self {
+        Self {
+            document,
+            rows: OnceCell::new(),
+        }
+    }
+
+    fn rows(&self) -> &Rows {
+        self.rows.get_or_init(|| Rows::new(&self.document))
+    }
+}
+
+impl Server {
+    fn run(&mut self) -> Result<()> {
+        let receiver = self.connection.receiver.clone();
+        for message in &receiver {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    self.request(request)?;
+                }
+                Message::Notification(notification) => {
+                    self.notification(notification)?
+                }
+                Message::Response(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn request(&self, request: Request) -> Result<()> {
+        let response = match request.method.as_str() {
+            HoverRequest::METHOD => {
+                self.respond::<HoverRequest>(request, Self::hover)
+            }
+            DocumentSymbolRequest::METHOD => {
+                self.respond::<DocumentSymbolRequest>(request, Self::symbols)
+            }
+            GotoDefinition::METHOD => {
+                self.respond::<GotoDefinition>(request, Self::definition)
+            }
+            method => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unsupported request {method}"),
+            ),
+        };
+        self.connection.sender.send(Message::Response(response))?;
+
+        Ok(())
+    }
+
+    fn respond<R: lsp_types::request::Request>(
+        &self,
+        request: Request,
+        handler: impl FnOnce(&Self, R::Params) -> R::Result,
+    ) -> Response {
+        let id = request.id.clone();
+        match request.extract::<R::Params>(R::METHOD) {
+            Ok((id, params)) => Response::new_ok(id, handler(self, params)),
+            Err(err) => Response::new_err(
+                id,
+                ErrorCode::InvalidParams as i32,
+                err.to_string(),
+            ),
+        }
+    }
+
+    fn notification(&mut self, notification: Notification) -> Result<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params = params::<DidOpenTextDocument>(notification)?;
+                let document = params.text_document;
+                self.documents.insert(
+                    document.uri.clone(),
+                    Open::new(Document::new(document.text)),
+                );
+                self.publish_diagnostics(document.uri, Some(document.version))
+            }
+            DidChangeTextDocument::METHOD => {
+                let params = params::<DidChangeTextDocument>(notification)?;
+                let uri = params.text_document.uri;
+                if let Some(open) = self.documents.get_mut(&uri) {
+                    open.rows.take();
+                    let document = &mut open.document;
+                    for change in params.content_changes {
+                        match change.range {
+                            Some(range) => {
+                                let lines = Lines::new(document);
+                                let range = lines.offset(range.start)
+                                    ..lines.offset(range.end);
+                                document.edit(&Edit {
+                                    range,
+                                    text: change.text,
+                                });
+                            }
+                            None => *document = Document::new(change.text),
+                        }
+                    }
+                }
+                self.publish_diagnostics(
+                    uri,
+                    Some(params.text_document.version),
+                )
+            }
+            DidCloseTextDocument::METHOD => {
+                let params = params::<DidCloseTextDocument>(notification)?;
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                self.publish_diagnostics(uri, None)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn publish_diagnostics(
+        &self,
+        uri: Uri,
+        version: Option<i32>,
+    ) -> Result<()> {
+        let diagnostics = self
+            .documents
+            .get(&uri)
+            .map(|Open { document, .. }| {
+                let lines = Lines::new(document);
+                document
+                    .errors()
+                    .iter()
+                    .map(|error| diagnostic(&lines, error))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let params = PublishDiagnosticsParams::new(uri, diagnostics, version);
+        self.connection.sender.send(Message::Notification(
+            Notification::new(PublishDiagnostics::METHOD.to_owned(), params),
+        ))?;
+
+        Ok(())
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let position = params.text_document_position_params;
+        let open = self.documents.get(&position.text_document.uri)?;
+        hover(open, position.position.line as usize)
+    }
+
+    fn symbols(
+        &self,
+        params: DocumentSymbolParams,
+    ) -> Option<DocumentSymbolResponse> {
+        let open = self.documents.get(&params.text_document.uri)?;
+        Some(DocumentSymbolResponse::Nested(symbols(&open.document)))
+    }
+
+    fn definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> Option<GotoDefinitionResponse> {
+        let position = params.text_document_position_params;
+        let open = self.documents.get(&position.text_document.uri)?;
+        let row = definition(open, position.position.line as usize)?;
+
+        Some(GotoDefinitionResponse::Scalar(Location::new(
+            position.text_document.uri,
+            row.selection,
+        )))
+    }
+}
+
+/// Converts between byte offsets and positions, which count UTF-16 code
+/// units.
+struct Lines<'a> {
+    text: &'a str,
+    starts: &'a [usize],
+}
+
+impl<'a> Lines<'a> {
+    fn new(document: &'a Document) -> Self {
+        Self {
+            text: document.text(),
+            starts: document.line_starts(),
+        }
+    }
+
+    /// The text of a line, without its line ending.
+    fn line(&self, line: usize) -> &'a str {
+        let (Some(&start), Some(&end)) =
+            (self.starts.get(line), self.starts.get(line + 1))
+        else {
+            return "";
+        };
+        let text = &self.text[start..end];
+        let text = text.strip_suffix('\n').unwrap_or(text);
+        text.strip_suffix('\r').unwrap_or(text)
+    }
+
+    fn offset(&self, position: Position) -> usize {
+        let Some(&start) = self.starts.get(position.line as usize) else {
+            return self.text.len();
+        };
+        let line = self.line(position.line as usize);
+
+        let mut units = 0;
+        for (i, c) in line.char_indices() {
+            if units >= position.character as usize {
+                return start + i;
+            }
+            units += c.len_utf16();
+        }
+
+        start + line.len()
+    }
+
+    fn position(&self, offset: usize) -> Position {
+        // The last start is the end of the text.
+        let starts = &self.starts[..self.starts.len() - 1];
+        let line = starts.partition_point(|&s| s <= offset).saturating_sub(1);
+        let start = starts.get(line).copied().unwrap_or(0);
+        if offset > start + self.line(line).len() {
+            return Position::new(self.starts.len() as u32 - 1, 0);
+        }
+
+        let character = self.text[start..offset].encode_utf16().count();
+        Position::new(line as u32, character as u32)
+    }
+
+    fn end(&self, line: u32) -> Position {
+        let character = self.line(line as usize).encode_utf16().count();
+        Position::new(line, character as u32)
+    }
+}
+
+fn params<N: lsp_types::notification::Notification>(
+    notification: Notification,
+) -> Result<N::Params> {
+    Ok(notification.extract(N::METHOD)?)
+}
+
+fn diagnostic(lines: &Lines, error: &Error) -> Diagnostic {
+    let start = lines.position(error.offset());
+    let mut message = error.message().to_owned();
+    if !error.attempted().is_empty() {
+        message.push_str("\nattempted: ");
+        message.push_str(&error.attempted().join(", "));
+    }
+
+    Diagnostic {
+        range: Range::new(start, lines.end(start.line)),
+        severity: Some(DiagnosticSeverity::ERROR),
+        source: Some("cwparse".to_owned()),
+        message,
+        ..Default::default()
+    }
+}
+
+/// The range of `span` within `line`.
+fn range(text: &str, line: &LocatedLine, span: Span) -> Range {
+    let character = |offset: usize| {
+        text[line.span.offset..offset].encode_utf16().count() as u32
+    };
+    let number = line.number as u32;
+
+    Range::new(
+        Position::new(number, character(span.offset)),
+        Position::new(number, character(span.offset + span.len)),
+    )
+}
+
+/// The range of the identifier of `line`, or of the whole line if it has
+/// none.
+fn selection(text: &str, line: &LocatedLine) -> Range {
+    let span = line
+        .fields
+        .iter()
+        .find(|field| field.kind == FieldKind::Identifier)
+        .map_or(line.span, |field| field.span);
+    range(text, line, span)
+}
+
+/// A row of a section layout.
+struct Row<'a> {
+    /// The range of the identifier of the row.
+    selection: Range,
+    section: SectionName<&'a str>,
+    symbol: Symbol<&'a str>,
+}
+
+/// The section layout rows of a document, by line and by the symbol they
+/// define.
+#[derive(Default)]
+struct Rows {
+    /// The line of the section title of each row, by the line of the row.
+    sections: HashMap<usize, usize>,
+    /// The line of the first row of each identifier and object.
+    definitions: HashMap<(Identifier<String>, String), usize>,
+}
+
+impl Rows {
+    fn new(document: &Document) -> Self {
+        let mut rows = Self::default();
+        let mut section = None;
+        for (number, line) in document.lines().enumerate() {
+            match line {
+                Line::SectionTitle(_) => section = Some(number),
+                Line::SectionSymbol(symbol) => {
+                    let Some(section) = section else { continue };
+                    rows.sections.insert(number, section);
+                    let key = (
+                        symbol.id.map_str(&mut str::to_owned),
+                        symbol.origin.obj.to_owned(),
+                    );
+                    rows.definitions.entry(key).or_insert(number);
+                }
+                _ => {}
+            }
+        }
+
+        rows
+    }
+
+    /// The row at `line`, if it is a section layout row.
+    fn row<'a>(&self, document: &'a Document, line: usize) -> Option<Row<'a>> {
+        let Some(Line::SectionTitle(section)) =
+            document.line(*self.sections.get(&line)?)
+        else {
+            return None;
+        };
+        let located = document.located_line(line)?;
+        let selection = selection(document.text(), &located);
+        let Line::SectionSymbol(symbol) = located.line else {
+            return None;
+        };
+
+        Some(Row {
+            selection,
+            section,
+            symbol,
+        })
+    }
+}
+
+/// Finds the section layout row of the closure tree node at `line`.
+fn definition(open: &Open, line: usize) -> Option<Row<'_>> {
+    let document = &open.document;
+    let Line::TreeNode(Node {
+        data: tree::Data::Object(id, specifier),
+        ..
+    }) = document.line(line)?
+    else {
+        return None;
+    };
+
+    let key = (
+        id.map_str(&mut str::to_owned),
+        specifier.origin.obj.to_owned(),
+    );
+    open.rows()
+        .row(document, *open.rows().definitions.get(&key)?)
+}
+
+fn hover(open: &Open, line: usize) -> Option<Hover> {
+    let document = &open.document;
+    let located = document.located_line(line)?;
+    let value = match &located.line {
+        Line::SectionSymbol(_) => {
+            describe_row(&open.rows().row(document, line)?)
+        }
+        Line::TreeNode(Node {
+            data: tree::Data::Object(id, specifier),
+            ..
+        }) => match definition(open, line) {
+            Some(row) => describe_row(&row),
+            None => describe_node(id, specifier),
+        },
+        Line::TreeNode(Node {
+            data: tree::Data::Linker(name),
+            ..
+        }) => format!("**`{name}`**\n\nLinker generated symbol"),
+        _ => return None,
+    };
+
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value,
+        }),
+        range: Some(selection(document.text(), &located)),
+    })
+}
+
+fn describe_row(row: &Row) -> String {
+    let symbol = &row.symbol;
+    let mut lines = vec![heading(&symbol.id)];
+    match &symbol.data {
+        section_table::Data::Parent { size, .. } => {
+            lines.push(format!("- Size: `{size:#x}`"))
+        }
+        section_table::Data::Child { parent } => {
+            lines.push(format!("- Entry of: `{parent}`"))
+        }
+    }
+    lines.push(format!("- Section: `{}`", row.section));
+    lines.push(format!("- Object: `{}`", object(&symbol.origin)));
+    lines.push(format!("- Address: `{:#010x}`", symbol.virt_addr));
+
+    lines.join("\n")
+}
+
+fn describe_node(
+    id: &Identifier<&str>,
+    specifier: &Specifier<&str>,
+) -> String {
+    [
+        heading(id),
+        format!("- Type: `{}`", specifier.r#type),
+        format!("- Scope: `{}`", specifier.scope),
+        format!("- Object: `{}`", object(&specifier.origin)),
+    ]
+    .join("\n")
+}
+
+/// The demangled name of `id` followed by its mangled name, if it is
+/// mangled.
+fn heading(id: &Identifier<&str>) -> String {
+    let name = id.to_string();
+    match demangle(&name) {
+        Some(demangled) => format!("**`{demangled}`**\n\n`{name}`\n"),
+        None => format!("**`{name}`**\n"),
+    }
+}
+
+fn object(origin: &Origin<&str>) -> String {
+    match origin.src {
+        Some(src) => format!("{} {src}", origin.obj),
+        None => origin.obj.to_owned(),
+    }
+}
+
+fn name(id: &Identifier<&str>) -> String {
+    let name = id.to_string();
+    demangle(&name).unwrap_or(name)
+}
+
+/// An outline of section layouts and closure trees, with rows nested under
+/// their parents.
+fn symbols(document: &Document) -> Vec<DocumentSymbol> {
+    let text = document.text();
+    let mut outline = Outline::default();
+
+    for line in document.located_lines() {
+        let range = range(text, &line, line.span);
+        let selection = selection(text, &line);
+        match &line.line {
+            Line::SectionTitle(name) => outline.push(
+                0,
+                symbol(
+                    name.to_string(),
+                    "section layout",
+                    SymbolKind::NAMESPACE,
+                )
+                .at(range, range),
+            ),
+            Line::TreeTitle(root) => outline.push(
+                0,
+                symbol(
+                    root.to_string(),
+                    "closure tree",
+                    SymbolKind::NAMESPACE,
+                )
+                .at(range, range),
+            ),
+            Line::SectionSymbol(row) => {
+                let level = match row.data {
+                    section_table::Data::Parent { .. } => 1,
+                    section_table::Data::Child { .. } => 2,
+                };
+                outline.push(
+                    level,
+                    symbol(
+                        name(&row.id),
+                        &object(&row.origin),
+                        SymbolKind::FUNCTION,
+                    )
+                    .at(range, selection),
+                );
+            }
+            Line::TreeNode(Node { depth, data }) => {
+                let symbol = match data {
+                    tree::Data::Object(id, specifier) => symbol(
+                        name(id),
+                        &object(&specifier.origin),
+                        match specifier.r#type {
+                            tree::Type::Function => SymbolKind::FUNCTION,
+                            tree::Type::Object => SymbolKind::VARIABLE,
+                            tree::Type::Section => SymbolKind::MODULE,
+                            tree::Type::None => SymbolKind::CONSTANT,
+                        },
+                    ),
+                    tree::Data::Linker(name) => symbol(
+                        name.to_string(),
+                        "linker generated symbol",
+                        SymbolKind::CONSTANT,
+                    ),
+                    tree::Data::DuplicateIdentifier(_)
+                    | tree::Data::DuplicateSpecifier(_) => continue,
+                };
+                outline.push(*depth, symbol.at(range, selection));
+            }
+            Line::MemoryTitle
+            | Line::LinkerTitle
+            | Line::MixedModeIslandsTitle
+            | Line::BranchIslandsTitle => outline.close(0),
+            _ => {}
+        }
+    }
+
+    outline.finish()
+}
+
+#[allow(deprecated)]
+fn symbol(name: String, detail: &str, kind: SymbolKind) -> DocumentSymbol {
+    DocumentSymbol {
+        name,
+        detail: Some(detail.to_owned()),
+        kind,
+        tags: None,
+        deprecated: None,
+        range: Range::default(),
+        selection_range: Range::default(),
+        children: None,
+    }
+}
+
+trait At {
+    fn at(self, range: Range, selection: Range) -> Self;
+}
+
+impl At for DocumentSymbol {
+    fn at(self, range: Range, selection_range: Range) -> Self {
+        Self {
+            range,
+            selection_range,
+            ..self
+        }
+    }
+}
+
+/// Nests symbols by their level, e.g. the depth of a closure tree node.
+#[derive(Default)]
+struct Outline {
+    symbols: Vec<DocumentSymbol>,
+    open: Vec<(u32, DocumentSymbol)>,
+}
+
+impl Outline {
+    fn push(&mut self, level: u32, symbol: DocumentSymbol) {
+        self.close(level);
+        self.open.push((level, symbol));
+    }
+
+    /// Closes every open symbol at `level` or deeper.
+    fn close(&mut self, level: u32) {
+        while self.open.last().is_some_and(|(open, _)| *open >= level) {
+            let (_, symbol) = self.open.pop().unwrap();
+            match self.open.last_mut() {
+                Some((_, parent)) => {
+                    parent.range.end = parent.range.end.max(symbol.range.end);
+                    parent.children.get_or_insert_with(Vec::new).push(symbol);
+                }
+                None => self.symbols.push(symbol),
+            }
+        }
+    }
+
+    fn finish(mut self) -> Vec<DocumentSymbol> {
+        self.close(0);
+        self.symbols
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{definition, hover, symbols, Lines, Open};
+    use cwparse::document::Document;
+    use lsp_types::{HoverContents, Position};
+
+    const INPUT: &str = "\
+Link map of __start\r\n\
+\x20 1] __start (func,global) found in __start.c.o \r\n\
+\x20  2] __dt__15CMemoryInStreamFv (func,weak) found in CMemoryInStream.o \r\n\
+\r\n\
+.text section layout\r\n\
+\x20 00000000 000010 80003100  4 __start\x20\t__start.c.o \r\n\
+\x20 00000010 00002c 80003110  4 __dt__15CMemoryInStreamFv\x20\tCMemoryInStream.o \r\n\
+\r\n\
+Memory map:\r\n\
+\x20           .text  80003100 000023a8 000001c0\r\n\
+";
+
+    #[test]
+    fn test_lines() {
+        let document = Document::new("ab\r\n\u{1f600}c\n".to_owned());
+        let lines = Lines::new(&document);
+        assert_eq!(lines.offset(Position::new(1, 2)), 8);
+        assert_eq!(lines.offset(Position::new(1, 9)), 9);
+        assert_eq!(lines.offset(Position::new(5, 0)), 10);
+        assert_eq!(lines.position(8), Position::new(1, 2));
+        assert_eq!(lines.position(10), Position::new(2, 0));
+    }
+
+    #[test]
+    fn test_language_features() {
+        let open = Open::new(Document::new(INPUT.to_owned()));
+        let document = &open.document;
+
+        let row = definition(&open, 2).unwrap();
+        assert_eq!(row.selection.start, Position::new(6, 30));
+
+        let Some(HoverContents::Markup(content)) =
+            hover(&open, 2).map(|hover| hover.contents)
+        else {
+            panic!("expected hover contents");
+        };
+        assert_eq!(
+            content.value,
+            "\
+**`CMemoryInStream::~CMemoryInStream()`**\n\n\
+`__dt__15CMemoryInStreamFv`\n\n\
+- Size: `0x2c`\n\
+- Section: `.text`\n\
+- Object: `CMemoryInStream.o`\n\
+- Address: `0x80003110`"
+        );
+
+        // Without a section row, the node itself is described as in the map.
+        let text = INPUT.split_inclusive('\n').take(2).collect();
+        let open = Open::new(Document::new(text));
+        let Some(HoverContents::Markup(content)) =
+            hover(&open, 1).map(|hover| hover.contents)
+        else {
+            panic!("expected hover contents");
+        };
+        assert!(content.value.contains("- Type: `func`"));
+        assert!(content.value.contains("- Scope: `global`"));
+
+        let symbols = symbols(document);
+        let names = symbols
+            .iter()
+            .map(|symbol| symbol.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["__start", ".text"]);
+        let tree = &symbols[0].children.as_ref().unwrap()[0];
+        assert_eq!(
+            tree.children.as_ref().unwrap()[0].name,
+            "CMemoryInStream::~CMemoryInStream()"
+        );
+        assert_eq!(symbols[0].range.end, Position::new(2, 71));
+        assert_eq!(symbols[1].children.as_ref().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,40 @@
+//! Streams a CodeWarrior map file as newline-delimited JSON, one object per
+//! line, e.g. `cwparse-ndjson GALE01.map | jq 'select(.type == "Unknown")'`.
+//!
+//! Reads from stdin if no path is given.
+
+use cwparse::ndjson::write_events;
+use std::{
+    env,
+    fs::File,
+    io::{self, BufReader, BufWriter},
+    process::ExitCode,
+};
+
+fn main() -> ExitCode {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let stdout = BufWriter::new(io::stdout().lock());
+
+    let result = match args.as_slice() {
+        [] => write_events(io::stdin().lock(), stdout),
+        [path] if path != "-" => File::open(path)
+            .and_then(|file| write_events(BufReader::new(file), stdout)),
+        [_] => write_events(io::stdin().lock(), stdout),
+        _ => {
+            eprintln!("usage: cwparse-ndjson [PATH]");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        // Stop quietly when the reader goes away, e.g. `| head`.
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => {
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
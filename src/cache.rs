@@ -0,0 +1,904 @@
+use crate::{
+    island_table::Island,
+    linker_table,
+    map::{
+        DebugSectionName, Dialect, Identifier, MappingSymbol, Origin,
+        SectionName,
+    },
+    map_file::{MapFile, Section, Tree},
+    memory_table, section_table,
+    tree::{self, Scope, Specifier, Type},
+};
+use memmap2::Mmap;
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    process, str,
+};
+
+pub const MAGIC: [u8; 4] = *b"CWPC";
+/// Bumped whenever the layout of the cache or of the document model changes.
+pub const VERSION: u32 = 9;
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+pub enum Error {
+    Magic,
+    Version(u32),
+    /// The cache was written for a different map.
+    Stale,
+    Truncated,
+    Invalid(&'static str),
+    /// A table or string does not fit in the 32-bit lengths of the format.
+    TooLarge,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Magic => f.write_str("Not a map cache"),
+            Error::Version(version) => {
+                write!(f, "Unsupported map cache version {version}")
+            }
+            Error::Stale => f.write_str("Map cache is out of date"),
+            Error::Truncated => f.write_str("Map cache is truncated"),
+            Error::Invalid(reason) => write!(f, "Invalid map cache: {reason}"),
+            Error::TooLarge => f.write_str("Map is too large to cache"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The 64-bit FNV-1a hash of a map's text, used to detect stale caches.
+pub fn hash(source: &str) -> u64 {
+    source.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// The path of the cache for the map at `path`, e.g. `GALE01.map.cache`.
+pub fn path(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".cache");
+    path.into()
+}
+
+/// Writes the cache of a parsed map next to the map itself.
+///
+/// The cache is written to a temporary file that then replaces the old cache,
+/// so that a cache mapped by [`load`] is never changed underneath it.
+pub fn save<S>(
+    map_path: &Path,
+    source: &str,
+    map_file: &MapFile<S>,
+) -> io::Result<()>
+where
+    S: Eq + PartialEq + AsRef<str>,
+{
+    let cache = write(source, map_file)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    let path = path(map_path);
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(format!(".{}.tmp", process::id()));
+    fs::write(&temp, cache)?;
+    fs::rename(&temp, &path).inspect_err(|_| {
+        let _ = fs::remove_file(&temp);
+    })
+}
+
+/// A cache file mapped into memory, as written by [`save`].
+pub struct Cache {
+    mmap: Mmap,
+}
+
+impl Cache {
+    /// Reads the parsed map. Its strings are borrowed from the mapped file,
+    /// while the records that refer to them are decoded into new lists.
+    ///
+    /// Fails with [`Error::Stale`] unless `source` is the text the cache was
+    /// written for.
+    pub fn map_file(&self, source: &str) -> Result<MapFile<&str>, Error> {
+        read(&self.mmap, source)
+    }
+}
+
+/// Maps the cache next to the map at `map_path` into memory.
+///
+/// The cache is only validated by [`Cache::map_file`].
+pub fn load(map_path: &Path) -> io::Result<Cache> {
+    let file = File::open(path(map_path))?;
+    // SAFETY: `save` replaces the cache with a new file rather than writing
+    // to it in place, so the mapped file is never changed by this crate.
+    // Every record is validated as it is read.
+    let mmap = unsafe { Mmap::map(&file) }?;
+
+    Ok(Cache { mmap })
+}
+
+/// Serializes a parsed map along with the hash of its source text.
+///
+/// The cache consists of a header, a table of every distinct string and the
+/// records of each table, which refer to strings by index. Fails with
+/// [`Error::TooLarge`] if any of them has more than `u32::MAX` entries or
+/// bytes.
+pub fn write<S>(source: &str, map_file: &MapFile<S>) -> Result<Vec<u8>, Error>
+where
+    S: Eq + PartialEq + AsRef<str>,
+{
+    let mut writer = Writer::default();
+    writer.map_file(map_file);
+    if writer.too_large {
+        return Err(Error::TooLarge);
+    }
+
+    let mut buf = Vec::with_capacity(writer.buf.len());
+    buf.extend_from_slice(&MAGIC);
+    buf.extend_from_slice(&VERSION.to_le_bytes());
+    buf.extend_from_slice(&hash(source).to_le_bytes());
+    buf.extend_from_slice(&len(writer.strings.len())?.to_le_bytes());
+    for s in &writer.strings {
+        buf.extend_from_slice(&len(s.len())?.to_le_bytes());
+        buf.extend_from_slice(s.as_bytes());
+    }
+    buf.extend_from_slice(&writer.buf);
+
+    Ok(buf)
+}
+
+fn len(len: usize) -> Result<u32, Error> {
+    u32::try_from(len).map_err(|_| Error::TooLarge)
+}
+
+/// Loads a cache written by [`write()`], borrowing every string from `data`.
+///
+/// Fails with [`Error::Stale`] unless `source` is the text the cache was
+/// written for.
+pub fn read<'a>(
+    data: &'a [u8],
+    source: &str,
+) -> Result<MapFile<&'a str>, Error> {
+    let mut reader = Reader {
+        data,
+        strings: Vec::new(),
+    };
+
+    if reader.bytes(4)? != MAGIC {
+        return Err(Error::Magic);
+    }
+    let version = reader.u32()?;
+    if version != VERSION {
+        return Err(Error::Version(version));
+    }
+    if reader.u64()? != hash(source) {
+        return Err(Error::Stale);
+    }
+
+    let count = reader.u32()?;
+    reader.strings = (0..count)
+        .map(|_| {
+            let len = reader.u32()?;
+            str::from_utf8(reader.bytes(len as usize)?)
+                .map_err(|_| Error::Invalid("String is not UTF-8"))
+        })
+        .collect::<Result<_, _>>()?;
+
+    let map_file = reader.map_file()?;
+    if !reader.data.is_empty() {
+        return Err(Error::Invalid("Trailing data"));
+    }
+
+    Ok(map_file)
+}
+
+#[derive(Default)]
+struct Writer<'s> {
+    buf: Vec<u8>,
+    strings: Vec<&'s str>,
+    indices: HashMap<&'s str, u32>,
+    /// Set instead of truncating a length that does not fit in a `u32`.
+    too_large: bool,
+}
+
+impl<'s> Writer<'s> {
+    fn u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn len(&mut self, len: usize) {
+        match self::len(len) {
+            Ok(len) => self.u32(len),
+            Err(_) => self.too_large = true,
+        }
+    }
+
+    fn str(&mut self, s: &'s str) {
+        let Ok(next) = len(self.strings.len()) else {
+            self.too_large = true;
+            return;
+        };
+        let index = *self.indices.entry(s).or_insert(next);
+        if index == next {
+            self.strings.push(s);
+        }
+        self.u32(index);
+    }
+
+    fn option<T>(&mut self, value: Option<T>, f: impl FnOnce(&mut Self, T)) {
+        match value {
+            Some(value) => {
+                self.u8(1);
+                f(self, value);
+            }
+            None => self.u8(0),
+        }
+    }
+
+    fn map_file<S>(&mut self, map_file: &'s MapFile<S>)
+    where
+        S: Eq + PartialEq + AsRef<str>,
+    {
+        self.u8(match map_file.dialect {
+            None => 0,
+            Some(Dialect::PowerPc) => 1,
+            Some(Dialect::Arm) => 2,
+        });
+
+        self.len(map_file.trees.len());
+        for tree in &map_file.trees {
+            self.str(tree.root.as_ref());
+            self.len(tree.nodes.len());
+            for node in &tree.nodes {
+                self.node(node);
+            }
+        }
+
+        self.len(map_file.sections.len());
+        for section in &map_file.sections {
+            self.section_name(&section.name);
+            self.len(section.symbols.len());
+            for symbol in &section.symbols {
+                self.symbol(symbol);
+            }
+        }
+
+        self.len(map_file.memory.len());
+        for entry in &map_file.memory {
+            self.memory_entry(entry);
+        }
+
+        self.len(map_file.linker.len());
+        for entry in &map_file.linker {
+            self.str(entry.name.as_ref());
+            self.u32(entry.virt_addr);
+            self.len(entry.padding);
+        }
+
+        for islands in [&map_file.mixed_mode_islands, &map_file.branch_islands]
+        {
+            self.len(islands.len());
+            for island in islands {
+                self.u32(island.virt_addr);
+                self.identifier(&island.name);
+                self.identifier(&island.target);
+            }
+        }
+
+        self.len(map_file.unknown.len());
+        for (number, text) in &map_file.unknown {
+            self.len(*number);
+            self.str(text.as_ref());
+        }
+    }
+
+    fn node<S>(&mut self, node: &'s tree::Node<S>)
+    where
+        S: Eq + PartialEq + AsRef<str>,
+    {
+        self.u32(node.depth);
+        match &node.data {
+            tree::Data::Linker(name) => {
+                self.u8(0);
+                self.str(name.as_ref());
+            }
+            tree::Data::Object(id, specifier) => {
+                self.u8(1);
+                self.identifier(id);
+                self.specifier(specifier);
+            }
+            tree::Data::DuplicateIdentifier(id) => {
+                self.u8(2);
+                self.identifier(id);
+            }
+            tree::Data::DuplicateSpecifier(specifier) => {
+                self.u8(3);
+                self.specifier(specifier);
+            }
+        }
+    }
+
+    fn specifier<S>(&mut self, specifier: &'s Specifier<S>)
+    where
+        S: Eq + PartialEq + AsRef<str>,
+    {
+        self.u8(match specifier.r#type {
+            Type::None => 0,
+            Type::Section => 1,
+            Type::Object => 2,
+            Type::Function => 3,
+        });
+        self.u8(match specifier.scope {
+            Scope::Global => 0,
+            Scope::Local => 1,
+            Scope::Weak => 2,
+        });
+        self.origin(&specifier.origin);
+    }
+
+    fn origin<S>(&mut self, origin: &'s Origin<S>)
+    where
+        S: Eq + PartialEq + AsRef<str>,
+    {
+        self.str(origin.obj.as_ref());
+        self.option(origin.src.as_ref(), |w, src| w.str(src.as_ref()));
+        self.u8(origin.asm as u8);
+    }
+
+    fn identifier<S>(&mut self, id: &'s Identifier<S>)
+    where
+        S: Eq + PartialEq + AsRef<str>,
+    {
+        match id {
+            Identifier::Relative { idx } => {
+                self.u8(0);
+                self.u32(*idx);
+            }
+            Identifier::StringBase { idx } => {
+                self.u8(1);
+                self.u8(*idx);
+            }
+            Identifier::Named { name, instance } => {
+                self.u8(2);
+                self.str(name.as_ref());
+                self.option(*instance, Self::u32);
+            }
+            Identifier::Mangled { name } => {
+                self.u8(3);
+                self.str(name.as_ref());
+            }
+            Identifier::Section { name, idx } => {
+                self.u8(4);
+                self.section_name(name);
+                self.option(*idx, Self::u8);
+            }
+            Identifier::DotL { name } => {
+                self.u8(5);
+                self.str(name.as_ref());
+            }
+            Identifier::Mapping(symbol) => {
+                self.u8(6);
+                self.u8(match symbol {
+                    MappingSymbol::Arm => 0,
+                    MappingSymbol::Thumb => 1,
+                    MappingSymbol::Data => 2,
+                });
+            }
+        }
+    }
+
+    fn section_name<S>(&mut self, name: &'s SectionName<S>)
+    where
+        S: AsRef<str>,
+    {
+        use SectionName::*;
+
+        let tag = match name {
+            Bss => 0,
+            Ctors(_) => 1,
+            Data => 2,
+            Dtors(_) => 3,
+            ExTab => 4,
+            ExTabIndex => 5,
+            Init => 6,
+            InitArray => 7,
+            FiniArray => 8,
+            RoData => 9,
+            SBss => 10,
+            SBss0 => 11,
+            SBss2 => 12,
+            SData => 13,
+            SData0 => 14,
+            SData2 => 15,
+            EmbSBss0 => 16,
+            EmbSData0 => 17,
+            EmbApuInfo => 18,
+            Text => 19,
+            Itcm => 20,
+            Dtcm => 21,
+            Overlay(_) => 22,
+            Unknown(_) => 23,
+        };
+        self.u8(tag);
+        match name {
+            Ctors(priority) | Dtors(priority) => {
+                self.option(*priority, Self::u16)
+            }
+            Overlay(name) | Unknown(name) => self.str(name.as_ref()),
+            _ => {}
+        }
+    }
+
+    fn debug_section_name<S>(&mut self, name: &'s DebugSectionName<S>)
+    where
+        S: AsRef<str>,
+    {
+        use DebugSectionName::*;
+
+        let tag = match name {
+            Main => 0,
+            Line => 1,
+            Abbrev => 2,
+            Aranges => 3,
+            Frame => 4,
+            Info => 5,
+            DebugLine => 6,
+            Loc => 7,
+            MacInfo => 8,
+            PubNames => 9,
+            PubTypes => 10,
+            Ranges => 11,
+            SfNames => 12,
+            SrcInfo => 13,
+            Str => 14,
+            Comment => 15,
+            MwCats(_) => 16,
+            Unknown(_) => 17,
+        };
+        self.u8(tag);
+        match name {
+            MwCats(name) => self.option(name.as_ref(), Self::section_name),
+            Unknown(name) => self.str(name.as_ref()),
+            _ => {}
+        }
+    }
+
+    fn symbol<S>(&mut self, symbol: &'s section_table::Symbol<S>)
+    where
+        S: Eq + PartialEq + AsRef<str>,
+    {
+        self.u32(symbol.addr);
+        self.u32(symbol.virt_addr);
+        self.option(symbol.file_addr, Self::u32);
+        match &symbol.data {
+            section_table::Data::Parent { size, align } => {
+                self.u8(0);
+                self.u32(*size);
+                self.u8(*align);
+            }
+            section_table::Data::Child { parent } => {
+                self.u8(1);
+                self.identifier(parent);
+            }
+        }
+        self.identifier(&symbol.id);
+        self.origin(&symbol.origin);
+        self.len(symbol.padding);
+    }
+
+    fn memory_entry<S>(&mut self, entry: &'s memory_table::Entry<S>)
+    where
+        S: AsRef<str>,
+    {
+        match &entry.data {
+            memory_table::Data::Main { name, virt_addr } => {
+                self.u8(0);
+                self.section_name(name);
+                self.u32(*virt_addr);
+            }
+            memory_table::Data::Debug { name } => {
+                self.u8(1);
+                self.debug_section_name(name);
+            }
+        }
+        self.u32(entry.size);
+        self.u32(entry.file_addr);
+        self.option(entry.rom.as_ref(), |writer, rom| {
+            writer.u32(rom.rom_addr);
+            writer.u32(rom.ram_buffer_addr);
+            writer.u32(rom.s_record_line);
+            writer.u32(rom.bin_file_addr);
+            writer.str(rom.bin_file_name.as_ref());
+        });
+        self.len(entry.padding);
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    strings: Vec<&'a str>,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.data.len() < len {
+            return Err(Error::Truncated);
+        }
+        let (bytes, data) = self.data.split_at(len);
+        self.data = data;
+
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        Ok(self.bytes(N)?.try_into().expect("Slice has length N"))
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Result<u64, Error> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    fn bool(&mut self) -> Result<bool, Error> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(Error::Invalid("Invalid boolean")),
+        }
+    }
+
+    fn str(&mut self) -> Result<&'a str, Error> {
+        let index = self.u32()?;
+        self.strings
+            .get(index as usize)
+            .copied()
+            .ok_or(Error::Invalid("String index is out of range"))
+    }
+
+    fn option<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, Error>,
+    ) -> Result<Option<T>, Error> {
+        match self.bool()? {
+            true => f(self).map(Some),
+            false => Ok(None),
+        }
+    }
+
+    fn vec<T>(
+        &mut self,
+        mut f: impl FnMut(&mut Self) -> Result<T, Error>,
+    ) -> Result<Vec<T>, Error> {
+        let len = self.u32()?;
+        // Every record takes up at least one byte.
+        if len as usize > self.data.len() {
+            return Err(Error::Truncated);
+        }
+        (0..len).map(|_| f(self)).collect()
+    }
+
+    fn map_file(&mut self) -> Result<MapFile<&'a str>, Error> {
+        let dialect = match self.u8()? {
+            0 => None,
+            1 => Some(Dialect::PowerPc),
+            2 => Some(Dialect::Arm),
+            _ => return Err(Error::Invalid("Invalid dialect")),
+        };
+        let trees = self.vec(|r| {
+            Ok(Tree {
+                root: r.str()?,
+                nodes: r.vec(Self::node)?,
+            })
+        })?;
+        let sections = self.vec(|r| {
+            Ok(Section {
+                name: r.section_name()?,
+                symbols: r.vec(Self::symbol)?,
+            })
+        })?;
+        let memory = self.vec(Self::memory_entry)?;
+        let linker = self.vec(|r| {
+            Ok(linker_table::Entry {
+                name: r.str()?,
+                virt_addr: r.u32()?,
+                padding: r.u32()? as usize,
+            })
+        })?;
+        let island = |r: &mut Self| {
+            Ok(Island {
+                virt_addr: r.u32()?,
+                name: r.identifier()?,
+                target: r.identifier()?,
+            })
+        };
+        let mixed_mode_islands = self.vec(island)?;
+        let branch_islands = self.vec(island)?;
+        let unknown = self.vec(|r| Ok((r.u32()? as usize, r.str()?)))?;
+
+        Ok(MapFile {
+            dialect,
+            trees,
+            sections,
+            memory,
+            linker,
+            mixed_mode_islands,
+            branch_islands,
+            unknown,
+        })
+    }
+
+    fn node(&mut self) -> Result<tree::Node<&'a str>, Error> {
+        let depth = self.u32()?;
+        let data = match self.u8()? {
+            0 => tree::Data::Linker(self.str()?),
+            1 => tree::Data::Object(self.identifier()?, self.specifier()?),
+            2 => tree::Data::DuplicateIdentifier(self.identifier()?),
+            3 => tree::Data::DuplicateSpecifier(self.specifier()?),
+            _ => return Err(Error::Invalid("Invalid tree node")),
+        };
+
+        Ok(tree::Node { depth, data })
+    }
+
+    fn specifier(&mut self) -> Result<Specifier<&'a str>, Error> {
+        let r#type = match self.u8()? {
+            0 => Type::None,
+            1 => Type::Section,
+            2 => Type::Object,
+            3 => Type::Function,
+            _ => return Err(Error::Invalid("Invalid symbol type")),
+        };
+        let scope = match self.u8()? {
+            0 => Scope::Global,
+            1 => Scope::Local,
+            2 => Scope::Weak,
+            _ => return Err(Error::Invalid("Invalid symbol scope")),
+        };
+
+        Ok(Specifier {
+            r#type,
+            scope,
+            origin: self.origin()?,
+        })
+    }
+
+    fn origin(&mut self) -> Result<Origin<&'a str>, Error> {
+        Ok(Origin {
+            obj: self.str()?,
+            src: self.option(Self::str)?,
+            asm: self.bool()?,
+        })
+    }
+
+    fn identifier(&mut self) -> Result<Identifier<&'a str>, Error> {
+        Ok(match self.u8()? {
+            0 => Identifier::Relative { idx: self.u32()? },
+            1 => Identifier::StringBase { idx: self.u8()? },
+            2 => Identifier::Named {
+                name: self.str()?,
+                instance: self.option(Self::u32)?,
+            },
+            3 => Identifier::Mangled { name: self.str()? },
+            4 => Identifier::Section {
+                name: self.section_name()?,
+                idx: self.option(Self::u8)?,
+            },
+            5 => Identifier::DotL { name: self.str()? },
+            6 => Identifier::Mapping(match self.u8()? {
+                0 => MappingSymbol::Arm,
+                1 => MappingSymbol::Thumb,
+                2 => MappingSymbol::Data,
+                _ => return Err(Error::Invalid("Invalid mapping symbol")),
+            }),
+            _ => return Err(Error::Invalid("Invalid identifier")),
+        })
+    }
+
+    fn section_name(&mut self) -> Result<SectionName<&'a str>, Error> {
+        use SectionName::*;
+
+        Ok(match self.u8()? {
+            0 => Bss,
+            1 => Ctors(self.option(Self::u16)?),
+            2 => Data,
+            3 => Dtors(self.option(Self::u16)?),
+            4 => ExTab,
+            5 => ExTabIndex,
+            6 => Init,
+            7 => InitArray,
+            8 => FiniArray,
+            9 => RoData,
+            10 => SBss,
+            11 => SBss0,
+            12 => SBss2,
+            13 => SData,
+            14 => SData0,
+            15 => SData2,
+            16 => EmbSBss0,
+            17 => EmbSData0,
+            18 => EmbApuInfo,
+            19 => Text,
+            20 => Itcm,
+            21 => Dtcm,
+            22 => Overlay(self.str()?),
+            23 => Unknown(self.str()?),
+            _ => return Err(Error::Invalid("Invalid section name")),
+        })
+    }
+
+    fn debug_section_name(
+        &mut self,
+    ) -> Result<DebugSectionName<&'a str>, Error> {
+        use DebugSectionName::*;
+
+        Ok(match self.u8()? {
+            0 => Main,
+            1 => Line,
+            2 => Abbrev,
+            3 => Aranges,
+            4 => Frame,
+            5 => Info,
+            6 => DebugLine,
+            7 => Loc,
+            8 => MacInfo,
+            9 => PubNames,
+            10 => PubTypes,
+            11 => Ranges,
+            12 => SfNames,
+            13 => SrcInfo,
+            14 => Str,
+            15 => Comment,
+            16 => MwCats(self.option(Self::section_name)?),
+            17 => Unknown(self.str()?),
+            _ => return Err(Error::Invalid("Invalid debug section name")),
+        })
+    }
+
+    fn symbol(&mut self) -> Result<section_table::Symbol<&'a str>, Error> {
+        let addr = self.u32()?;
+        let virt_addr = self.u32()?;
+        let file_addr = self.option(Self::u32)?;
+        let data = match self.u8()? {
+            0 => section_table::Data::Parent {
+                size: self.u32()?,
+                align: self.u8()?,
+            },
+            1 => section_table::Data::Child {
+                parent: self.identifier()?,
+            },
+            _ => return Err(Error::Invalid("Invalid section symbol")),
+        };
+
+        Ok(section_table::Symbol {
+            addr,
+            virt_addr,
+            file_addr,
+            data,
+            id: self.identifier()?,
+            origin: self.origin()?,
+            padding: self.u32()? as usize,
+        })
+    }
+
+    fn memory_entry(&mut self) -> Result<memory_table::Entry<&'a str>, Error> {
+        let data = match self.u8()? {
+            0 => memory_table::Data::Main {
+                name: self.section_name()?,
+                virt_addr: self.u32()?,
+            },
+            1 => memory_table::Data::Debug {
+                name: self.debug_section_name()?,
+            },
+            _ => return Err(Error::Invalid("Invalid memory map entry")),
+        };
+
+        Ok(memory_table::Entry {
+            data,
+            size: self.u32()?,
+            file_addr: self.u32()?,
+            rom: self.option(|reader| {
+                Ok(memory_table::Rom {
+                    rom_addr: reader.u32()?,
+                    ram_buffer_addr: reader.u32()?,
+                    s_record_line: reader.u32()?,
+                    bin_file_addr: reader.u32()?,
+                    bin_file_name: reader.str()?,
+                })
+            })?,
+            padding: self.u32()? as usize,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load, read, save, write, Error, Writer};
+    use crate::{
+        map_file::{map_file, MapFile},
+        utils::test_utils::assert_diff,
+    };
+    use nom_supreme::error::ErrorTree;
+
+    #[test]
+    fn test_cache() {
+        let input = "\
+Link map of __start\r\n\
+\x20 1] __start (func,global) found in __start.c.o \r\n\
+\x20  2] ...data.0 (notype,local) found in OSCache.c.o \r\n\
+\x20   3] _stack_addr found as linker generated symbol\r\n\
+\x20      6] vprintf (notype,global) found in MSL_C.a printf.o (asm)\r\n\
+\r\n\
+.ctors$10 section layout\r\n\
+\x20 00000000 0000f0 80003100  4 __start\x20\t__start.c.o \r\n\
+\x20 00000250 000000 80003350 __fill_mem (entry of memset) \t__mem.o \r\n\
+\r\n\
+Memory map:\r\n\
+\x20           .init  80003100 000000f0 000001c0\r\n\
+\x20    .mwcats.text           000040 000002c0\r\n\
+\r\n\
+Linker generated symbols:\r\n\
+\x20          _db_stack_addr 804f0c00\r\n\
+\r\n\
+Branch Islands\r\n\
+\x20 80003110 branch island @1234 created for OSReport\r\n\
+";
+
+        let expected = map_file::<ErrorTree<&str>>(input).unwrap();
+        let cache = write(input, &expected).unwrap();
+
+        match read(&cache, input) {
+            Ok(actual) => assert_diff(&expected, &actual),
+            Err(err) => panic!("{err}"),
+        }
+        assert_eq!(read(&cache, "").unwrap_err(), Error::Stale);
+        assert_eq!(
+            read(&cache[..cache.len() - 1], input).unwrap_err(),
+            Error::Truncated
+        );
+        assert_eq!(read(b"nope", input).unwrap_err(), Error::Magic);
+
+        let dir = tempfile::tempdir().unwrap();
+        let map_path = dir.path().join("GALE01.map");
+        save(&map_path, input, &expected).unwrap();
+        let cache = load(&map_path).unwrap();
+        assert_diff(&expected, &cache.map_file(input).unwrap());
+
+        // Saving again replaces the file instead of changing the mapped one.
+        save(&map_path, "", &MapFile::<&str>::default()).unwrap();
+        assert_diff(&expected, &cache.map_file(input).unwrap());
+        assert_eq!(
+            load(&map_path).unwrap().map_file(input).unwrap_err(),
+            Error::Stale
+        );
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn test_too_large() {
+        let mut writer = Writer::default();
+        writer.len(u32::MAX as usize);
+        assert!(!writer.too_large);
+        writer.len(u32::MAX as usize + 1);
+        assert!(writer.too_large);
+    }
+}
@@ -0,0 +1,252 @@
+//! Demangling of CodeWarrior C++ symbol names, e.g.
+//! `__dt__15CMemoryInStreamFv` as `CMemoryInStream::~CMemoryInStream()`.
+
+/// Demangles `name`, or returns [`None`] if it is not a mangled name.
+pub fn demangle(name: &str) -> Option<String> {
+    // The base name may itself start with underscores, e.g. `__ct`.
+    let skip = name.len() - name.trim_start_matches('_').len();
+
+    name[skip..].match_indices("__").find_map(|(i, _)| {
+        let (base, rest) = name.split_at(skip + i);
+        symbol(base, &rest[2..])
+    })
+}
+
+fn symbol(base: &str, rest: &str) -> Option<String> {
+    if base.is_empty() {
+        return None;
+    }
+
+    let mut input = Input(rest);
+    let class = match input.peek() {
+        Some('Q' | '0'..='9') => Some(input.qualified_name()?),
+        _ => None,
+    };
+    let is_const = class.is_some() && input.eat('C');
+    let params = if input.eat('F') {
+        Some(input.params()?)
+    } else {
+        None
+    };
+    if !input.0.is_empty() || (class.is_none() && params.is_none()) {
+        return None;
+    }
+
+    let last = class.as_deref().map(|class| {
+        let last = class.rsplit("::").next().unwrap_or(class);
+        // Drop any template arguments for constructor names.
+        last.split('<').next().unwrap_or(last)
+    });
+    let name = match (base, last) {
+        ("__ct", Some(last)) => last.to_owned(),
+        ("__dt", Some(last)) => format!("~{last}"),
+        _ => operator(base).map_or_else(|| base.to_owned(), str::to_owned),
+    };
+
+    let mut demangled = match class {
+        Some(class) => format!("{class}::{name}"),
+        None => name,
+    };
+    if let Some(params) = params {
+        demangled.push_str(&format!("({params})"));
+    }
+    if is_const {
+        demangled.push_str(" const");
+    }
+
+    Some(demangled)
+}
+
+fn operator(base: &str) -> Option<&'static str> {
+    Some(match base {
+        "__nw" => "operator new",
+        "__dl" => "operator delete",
+        "__nwa" => "operator new[]",
+        "__dla" => "operator delete[]",
+        "__pl" => "operator+",
+        "__mi" => "operator-",
+        "__ml" => "operator*",
+        "__dv" => "operator/",
+        "__md" => "operator%",
+        "__er" => "operator^",
+        "__ad" => "operator&",
+        "__or" => "operator|",
+        "__co" => "operator~",
+        "__nt" => "operator!",
+        "__as" => "operator=",
+        "__lt" => "operator<",
+        "__gt" => "operator>",
+        "__apl" => "operator+=",
+        "__ami" => "operator-=",
+        "__amu" => "operator*=",
+        "__adv" => "operator/=",
+        "__amd" => "operator%=",
+        "__aer" => "operator^=",
+        "__aad" => "operator&=",
+        "__aor" => "operator|=",
+        "__ls" => "operator<<",
+        "__rs" => "operator>>",
+        "__als" => "operator<<=",
+        "__ars" => "operator>>=",
+        "__eq" => "operator==",
+        "__ne" => "operator!=",
+        "__le" => "operator<=",
+        "__ge" => "operator>=",
+        "__aa" => "operator&&",
+        "__oo" => "operator||",
+        "__pp" => "operator++",
+        "__mm" => "operator--",
+        "__cm" => "operator,",
+        "__rm" => "operator->*",
+        "__rf" => "operator->",
+        "__cl" => "operator()",
+        "__vc" => "operator[]",
+        _ => return None,
+    })
+}
+
+struct Input<'a>(&'a str);
+
+impl<'a> Input<'a> {
+    fn peek(&self) -> Option<char> {
+        self.0.chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        match self.0.strip_prefix(c) {
+            Some(rest) => {
+                self.0 = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn number(&mut self) -> Option<usize> {
+        let len = self.0.find(|c: char| !c.is_ascii_digit())?;
+        let (digits, rest) = self.0.split_at(len);
+        self.0 = rest;
+        digits.parse().ok()
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a str> {
+        let part = self.0.get(..len)?;
+        self.0 = &self.0[len..];
+        Some(part)
+    }
+
+    /// A length-prefixed name, e.g. `15CMemoryInStream`.
+    fn name(&mut self) -> Option<&'a str> {
+        let len = self.number()?;
+        self.take(len).filter(|name| !name.is_empty())
+    }
+
+    /// A name or a `Q`-prefixed list of names, e.g. `Q24Foo3Bar`.
+    fn qualified_name(&mut self) -> Option<String> {
+        if !self.eat('Q') {
+            return self.name().map(str::to_owned);
+        }
+
+        let count = self.take(1)?.parse::<usize>().ok()?;
+        let names = (0..count)
+            .map(|_| self.name())
+            .collect::<Option<Vec<_>>>()?;
+        Some(names.join("::"))
+    }
+
+    fn params(&mut self) -> Option<String> {
+        let mut params = Vec::new();
+        while !self.0.is_empty() && !self.0.starts_with('_') {
+            params.push(self.r#type()?);
+        }
+
+        Some(match params.as_slice() {
+            [void] if void == "void" => String::new(),
+            _ => params.join(", "),
+        })
+    }
+
+    fn r#type(&mut self) -> Option<String> {
+        let c = self.peek()?;
+        if c == 'Q' || c.is_ascii_digit() {
+            return self.qualified_name();
+        }
+        self.0 = &self.0[c.len_utf8()..];
+
+        Some(match c {
+            'C' => qualify(self.r#type()?, "const"),
+            'V' => qualify(self.r#type()?, "volatile"),
+            'U' => format!("unsigned {}", self.r#type()?),
+            'S' => format!("signed {}", self.r#type()?),
+            'P' if self.eat('F') => {
+                let (params, ret) = self.function()?;
+                format!("{ret} (*)({params})")
+            }
+            'P' => format!("{}*", self.r#type()?),
+            'R' => format!("{}&", self.r#type()?),
+            'F' => {
+                let (params, ret) = self.function()?;
+                format!("{ret} ({params})")
+            }
+            'A' => {
+                let len = self.number()?;
+                self.eat('_').then_some(())?;
+                format!("{}[{len}]", self.r#type()?)
+            }
+            'v' => "void".to_owned(),
+            'b' => "bool".to_owned(),
+            'c' => "char".to_owned(),
+            'w' => "wchar_t".to_owned(),
+            's' => "short".to_owned(),
+            'i' => "int".to_owned(),
+            'l' => "long".to_owned(),
+            'x' => "long long".to_owned(),
+            'f' => "float".to_owned(),
+            'd' => "double".to_owned(),
+            'r' => "long double".to_owned(),
+            'e' => "...".to_owned(),
+            _ => return None,
+        })
+    }
+
+    /// The parameters and return type of a function type, e.g. `iPc_v`.
+    fn function(&mut self) -> Option<(String, String)> {
+        let params = self.params()?;
+        self.eat('_').then_some(())?;
+        Some((params, self.r#type()?))
+    }
+}
+
+fn qualify(r#type: String, qualifier: &str) -> String {
+    if r#type.ends_with(['*', '&']) {
+        format!("{type} {qualifier}")
+    } else {
+        format!("{qualifier} {type}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::demangle;
+    use test_case::test_case;
+
+    #[test_case("__dt__15CMemoryInStreamFv", "CMemoryInStream::~CMemoryInStream()" ; "destructor")]
+    #[test_case("__ct__Q24Util6VectorFfff", "Util::Vector::Vector(float, float, float)" ; "qualified constructor")]
+    #[test_case("GetSize__10CResLoaderCFv", "CResLoader::GetSize() const" ; "const method")]
+    #[test_case("OSReport__FPCce", "OSReport(const char*, ...)" ; "variadic")]
+    #[test_case("__as__6CColorFRC6CColor", "CColor::operator=(const CColor&)" ; "operator")]
+    #[test_case("Sort__FPUlPFPCvPCv_i", "Sort(unsigned long*, int (*)(const void*, const void*))" ; "function pointer")]
+    #[test_case("sInstance__7CSystem", "CSystem::sInstance" ; "static member")]
+    #[test_case("Fill__FRA4_i", "Fill(int[4]&)" ; "array")]
+    fn test_demangle(mangled: &str, expected: &str) {
+        assert_eq!(demangle(mangled).as_deref(), Some(expected));
+    }
+
+    #[test_case("__start" ; "leading underscores")]
+    #[test_case("memset" ; "plain")]
+    #[test_case("__init_registers" ; "not a suffix")]
+    #[test_case("foo__Fz" ; "unknown type")]
+    fn test_not_mangled(name: &str) {
+        assert_eq!(demangle(name), None);
+    }
+}
@@ -0,0 +1,674 @@
+use crate::{
+    error::Error,
+    island_table::Island,
+    linker_table,
+    map::{Context, Dialect, Line, LineParser, SectionName},
+    map_file::{Builder, MapFile},
+    memory_table, section_table,
+    span::{Field, LocatedLine, Span},
+    tree,
+};
+use std::{collections::HashSet, ops::Range};
+
+/// Replaces a byte range of a [`Document`] with new text.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct Edit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+/// A row of the model of a [`Document`], along with the title of its table
+/// where rows refer to it.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub enum Item<S: Eq + PartialEq> {
+    TreeNode {
+        root: S,
+        node: tree::Node<S>,
+    },
+    SectionSymbol {
+        section: SectionName<S>,
+        symbol: section_table::Symbol<S>,
+    },
+    MemoryEntry(memory_table::Entry<S>),
+    LinkerEntry(linker_table::Entry<S>),
+    MixedModeIsland(Island<S>),
+    BranchIsland(Island<S>),
+    /// A line that could not be parsed.
+    Unknown(S),
+}
+
+/// What an edit changed: the `old` lines from before the edit were replaced
+/// by the re-parsed `new` lines, which changed the items of the model.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct Change {
+    pub old: Range<usize>,
+    pub new: Range<usize>,
+    /// The items that were added or that differ from before the edit.
+    pub changed: Vec<Item<String>>,
+    /// The items from before the edit that are no longer in the model.
+    pub removed: Vec<Item<String>>,
+}
+
+#[derive(Debug, Clone)]
+struct ParsedLine {
+    /// The length of the line in bytes, including its line ending.
+    len: usize,
+    /// The table the line was parsed in.
+    context: Context,
+    /// With spans relative to the start of the line.
+    line: Line<Span>,
+    /// Relative to the start of the line.
+    fields: Vec<Field>,
+    /// Relative to the start of the line.
+    error: Option<Error>,
+}
+
+/// A table title and the lines up to the next one. The lines before the
+/// first title form a table of their own.
+#[derive(Debug, Clone)]
+struct Table {
+    /// The number of lines in the table.
+    lines: usize,
+    dialect: Option<Dialect>,
+    /// The number of items the table added to each list of the model.
+    counts: Counts,
+}
+
+/// The lengths of each list of a [`MapFile`].
+#[derive(Debug, Default, Clone, Copy)]
+struct Counts {
+    trees: usize,
+    sections: usize,
+    memory: usize,
+    linker: usize,
+    mixed_mode_islands: usize,
+    branch_islands: usize,
+    unknown: usize,
+}
+
+impl Counts {
+    fn of<S: Eq + PartialEq>(map_file: &MapFile<S>) -> Self {
+        Self {
+            trees: map_file.trees.len(),
+            sections: map_file.sections.len(),
+            memory: map_file.memory.len(),
+            linker: map_file.linker.len(),
+            mixed_mode_islands: map_file.mixed_mode_islands.len(),
+            branch_islands: map_file.branch_islands.len(),
+            unknown: map_file.unknown.len(),
+        }
+    }
+
+    fn sum<'a>(tables: impl IntoIterator<Item = &'a Table>) -> Self {
+        tables.into_iter().fold(Self::default(), |sum, table| Self {
+            trees: sum.trees + table.counts.trees,
+            sections: sum.sections + table.counts.sections,
+            memory: sum.memory + table.counts.memory,
+            linker: sum.linker + table.counts.linker,
+            mixed_mode_islands: sum.mixed_mode_islands
+                + table.counts.mixed_mode_islands,
+            branch_islands: sum.branch_islands + table.counts.branch_islands,
+            unknown: sum.unknown + table.counts.unknown,
+        })
+    }
+}
+
+/// A map that is kept parsed as it is edited, e.g. in an editor.
+///
+/// An edit only re-parses the lines it touches, along with any following
+/// lines whose table changed as a result, e.g. after a table title was
+/// edited. The model is then only rebuilt for the tables of those lines.
+#[derive(Debug, Clone)]
+pub struct Document {
+    text: String,
+    lines: Vec<ParsedLine>,
+    /// The byte offset of each line, followed by the length of the text.
+    starts: Vec<usize>,
+    tables: Vec<Table>,
+    /// The items of every table, in the order of the tables.
+    model: MapFile<String>,
+}
+
+impl Document {
+    pub fn new(text: String) -> Self {
+        let lines = parse_lines(&text, LineParser::new());
+        let starts = line_starts(0, &lines);
+        let mut document = Self {
+            text,
+            lines,
+            starts,
+            tables: Vec::new(),
+            model: MapFile::default(),
+        };
+        let (tables, model) = document.build(0..document.lines.len());
+        document.tables = tables;
+        document.model = model;
+        document.model.dialect = document.dialect();
+
+        document
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Applies `edit`, returning the lines that were re-parsed and the items
+    /// of the model that changed.
+    ///
+    /// Panics if the range of the edit is out of bounds or does not lie on
+    /// char boundaries, like [`String::replace_range`].
+    pub fn edit(&mut self, edit: &Edit) -> Change {
+        let Range { start, end } = edit.range.clone();
+        let starts = &self.starts;
+
+        // The lines that contain any part of the range, or the position of
+        // an insertion.
+        let mut first = starts.partition_point(|&s| s <= start) - 1;
+        if first == self.lines.len() && first > 0 && !self.text.ends_with('\n')
+        {
+            first -= 1;
+        }
+        let first = first.min(self.lines.len());
+        let mut last = starts
+            .partition_point(|&s| s < end.max(start + 1))
+            .clamp(first, self.lines.len());
+
+        let region_start = starts[first];
+        let mut region_end = starts[last];
+        self.text.replace_range(start..end, &edit.text);
+        let delta = edit.text.len() as isize - (end - start) as isize;
+        region_end = (region_end as isize + delta) as usize;
+
+        // Deleting a line ending joins the next line onto the region.
+        while last < self.lines.len()
+            && region_end > region_start
+            && !self.text[..region_end].ends_with('\n')
+        {
+            region_end += self.lines[last].len;
+            last += 1;
+        }
+
+        let context = match first.checked_sub(1) {
+            Some(prev) => {
+                let prev = &self.lines[prev];
+                prev.context.next(&prev.line)
+            }
+            None => Context::None,
+        };
+        let mut parser = LineParser::with_context(context);
+        let mut lines = self.text[region_start..region_end]
+            .split_inclusive('\n')
+            .map(|text| parse_line(&mut parser, text))
+            .collect::<Vec<_>>();
+
+        // Keep going until the following lines are back in the table they
+        // were parsed in.
+        let mut offset = region_end;
+        while last < self.lines.len()
+            && self.lines[last].context != parser.context()
+        {
+            let len = self.lines[last].len;
+            lines.push(parse_line(
+                &mut parser,
+                &self.text[offset..offset + len],
+            ));
+            offset += len;
+            last += 1;
+        }
+
+        let old = first..last;
+        let new = first..first + lines.len();
+        let starts = line_starts(region_start, &lines);
+        self.starts.splice(first..last + 1, starts);
+        for start in &mut self.starts[new.end + 1..] {
+            *start = (*start as isize + delta) as usize;
+        }
+        self.lines.splice(old.clone(), lines);
+
+        let (changed, removed) = self.rebuild(old.clone(), new.clone());
+
+        Change {
+            old,
+            new,
+            changed,
+            removed,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// The parsed line at `index`, or [`Line::Unknown`] if it has an error.
+    pub fn line(&self, index: usize) -> Option<Line<&str>> {
+        let start = self.line_start(index)?;
+        Some(self.resolve(start, self.lines.get(index)?))
+    }
+
+    /// The parsed line at `index`, along with the location of its fields
+    /// within the document.
+    pub fn located_line(&self, index: usize) -> Option<LocatedLine<'_>> {
+        let start = self.line_start(index)?;
+        Some(self.locate(index, start, self.lines.get(index)?))
+    }
+
+    pub fn located_lines(&self) -> impl Iterator<Item = LocatedLine<'_>> {
+        self.lines
+            .iter()
+            .zip(&self.starts)
+            .enumerate()
+            .map(|(index, (line, &start))| self.locate(index, start, line))
+    }
+
+    fn locate(
+        &self,
+        index: usize,
+        start: usize,
+        line: &ParsedLine,
+    ) -> LocatedLine<'_> {
+        let text = &self.text[start..start + line.len];
+        let text = text.strip_suffix('\n').unwrap_or(text);
+        let text = text.strip_suffix('\r').unwrap_or(text);
+        let resolved = self.resolve(start, line);
+
+        LocatedLine {
+            number: index,
+            span: Span::locate(&self.text, text),
+            fields: line
+                .fields
+                .iter()
+                .map(|field| Field {
+                    kind: field.kind,
+                    span: Span {
+                        offset: start + field.span.offset,
+                        len: field.span.len,
+                    },
+                })
+                .collect(),
+            line: resolved,
+        }
+    }
+
+    /// The byte offset of the line at `index`, or of the end of the text if
+    /// `index` is the number of lines.
+    pub fn line_start(&self, index: usize) -> Option<usize> {
+        self.starts.get(index).copied()
+    }
+
+    /// The index of the line containing the byte at `offset`.
+    pub fn line_at(&self, offset: usize) -> usize {
+        self.starts
+            .partition_point(|&s| s <= offset)
+            .saturating_sub(1)
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = Line<&str>> {
+        self.lines
+            .iter()
+            .zip(&self.starts)
+            .map(|(line, &start)| self.resolve(start, line))
+    }
+
+    /// The errors of every line that could not be parsed.
+    pub fn errors(&self) -> Vec<Error> {
+        self.starts
+            .iter()
+            .zip(&self.lines)
+            .filter_map(|(&start, line)| {
+                let error = line.error.as_ref()?;
+                Some(error.relocate(&self.text, start))
+            })
+            .collect()
+    }
+
+    /// The tables of the parsed lines, leaving out any rows that are outside
+    /// of their table, like `map_file_lenient`. Lines that could not be
+    /// parsed are kept as unknown lines.
+    pub fn map_file(&self) -> &MapFile<String> {
+        &self.model
+    }
+
+    /// The byte offset of each line, followed by the length of the text.
+    pub fn line_starts(&self) -> &[usize] {
+        &self.starts
+    }
+
+    fn resolve(&self, start: usize, line: &ParsedLine) -> Line<&str> {
+        let text = &self.text[start..start + line.len];
+        line.line
+            .clone()
+            .map_str(&mut |span: Span| &text[span.range()])
+    }
+
+    /// Assembles the model of the tables that start within `lines`, which
+    /// must start at a table title or at the start of the document.
+    fn build(&self, lines: Range<usize>) -> (Vec<Table>, MapFile<String>) {
+        let mut tables = Vec::new();
+        let mut model = MapFile::default();
+
+        let mut start = lines.start;
+        while start < lines.end {
+            let end = (start + 1..lines.end)
+                .find(|&index| self.lines[index].line.is_title())
+                .unwrap_or(lines.end);
+
+            let mut builder = Builder::default();
+            let mut dialect = None;
+            for index in start..end {
+                let line =
+                    self.resolve(self.starts[index], &self.lines[index]);
+                dialect = dialect.max(line.dialect());
+                let _ = builder.push(line);
+            }
+            let mut table = builder.finish().map_str(&mut str::to_owned);
+            for (number, _) in &mut table.unknown {
+                *number += start;
+            }
+
+            tables.push(Table {
+                lines: end - start,
+                dialect,
+                counts: Counts::of(&table),
+            });
+            model.append(table);
+            start = end;
+        }
+
+        (tables, model)
+    }
+
+    /// Rebuilds the model of the tables that contained the `old` lines, which
+    /// were re-parsed as the `new` lines, returning the items that changed
+    /// and that were removed.
+    fn rebuild(
+        &mut self,
+        old: Range<usize>,
+        new: Range<usize>,
+    ) -> (Vec<Item<String>>, Vec<Item<String>>) {
+        let table_starts = self
+            .tables
+            .iter()
+            .scan(0, |start, table| {
+                let table_start = *start;
+                *start += table.lines;
+                Some(table_start)
+            })
+            .collect::<Vec<_>>();
+
+        // The table before the old lines is included, as it takes over the
+        // lines of a table whose title was removed.
+        let first = table_starts
+            .partition_point(|&s| s <= old.start.saturating_sub(1))
+            .saturating_sub(1);
+        let last = table_starts
+            .partition_point(|&s| s < old.end.max(old.start + 1))
+            .max(first);
+        let lines_start = table_starts.get(first).copied().unwrap_or(0);
+        let lines_end = match table_starts.get(last) {
+            Some(&start) => start,
+            None => self.lines.len() + old.len() - new.len(),
+        };
+
+        let (tables, model) =
+            self.build(lines_start..lines_end + new.len() - old.len());
+        let before = Counts::sum(&self.tables[..first]);
+        let replaced = Counts::sum(&self.tables[first..last]);
+        let counts = Counts::of(&model);
+        let changed = items(&model);
+
+        macro_rules! splice {
+            ($list:ident) => {
+                self.model
+                    .$list
+                    .splice(
+                        before.$list..before.$list + replaced.$list,
+                        model.$list,
+                    )
+                    .collect()
+            };
+        }
+        let removed = MapFile {
+            dialect: None,
+            trees: splice!(trees),
+            sections: splice!(sections),
+            memory: splice!(memory),
+            linker: splice!(linker),
+            mixed_mode_islands: splice!(mixed_mode_islands),
+            branch_islands: splice!(branch_islands),
+            unknown: splice!(unknown),
+        };
+        for (number, _) in
+            &mut self.model.unknown[before.unknown + counts.unknown..]
+        {
+            *number = *number + new.end - old.end;
+        }
+        self.tables.splice(first..last, tables);
+        self.model.dialect = self.dialect();
+
+        let removed = items(&removed);
+        let (changed_set, removed_set) = (
+            changed.iter().collect::<HashSet<_>>(),
+            removed.iter().collect::<HashSet<_>>(),
+        );
+
+        (
+            changed
+                .iter()
+                .filter(|item| !removed_set.contains(item))
+                .cloned()
+                .collect(),
+            removed
+                .iter()
+                .filter(|item| !changed_set.contains(item))
+                .cloned()
+                .collect(),
+        )
+    }
+
+    fn dialect(&self) -> Option<Dialect> {
+        self.tables.iter().filter_map(|table| table.dialect).max()
+    }
+}
+
+/// The rows of every table of `map_file`.
+fn items(map_file: &MapFile<String>) -> Vec<Item<String>> {
+    let nodes = map_file.trees.iter().flat_map(|tree| {
+        tree.nodes.iter().map(|node| Item::TreeNode {
+            root: tree.root.clone(),
+            node: node.clone(),
+        })
+    });
+    let symbols =
+        map_file
+            .symbols()
+            .map(|(section, symbol)| Item::SectionSymbol {
+                section: section.clone(),
+                symbol: symbol.clone(),
+            });
+
+    nodes
+        .chain(symbols)
+        .chain(map_file.memory.iter().cloned().map(Item::MemoryEntry))
+        .chain(map_file.linker.iter().cloned().map(Item::LinkerEntry))
+        .chain(
+            map_file
+                .mixed_mode_islands
+                .iter()
+                .cloned()
+                .map(Item::MixedModeIsland),
+        )
+        .chain(
+            map_file
+                .branch_islands
+                .iter()
+                .cloned()
+                .map(Item::BranchIsland),
+        )
+        .chain(
+            map_file
+                .unknown
+                .iter()
+                .map(|(_, text)| Item::Unknown(text.clone())),
+        )
+        .collect()
+}
+
+/// The byte offset of each of `lines`, starting at `start`, followed by the
+/// offset of their end.
+fn line_starts(start: usize, lines: &[ParsedLine]) -> Vec<usize> {
+    let mut starts = Vec::with_capacity(lines.len() + 1);
+    starts.push(start);
+    starts.extend(lines.iter().scan(start, |start, line| {
+        *start += line.len;
+        Some(*start)
+    }));
+
+    starts
+}
+
+fn parse_lines(text: &str, mut parser: LineParser) -> Vec<ParsedLine> {
+    text.split_inclusive('\n')
+        .map(|text| parse_line(&mut parser, text))
+        .collect()
+}
+
+fn parse_line(parser: &mut LineParser, text: &str) -> ParsedLine {
+    let context = parser.context();
+    let body = text.strip_suffix('\n').unwrap_or(text);
+    let body = body.strip_suffix('\r').unwrap_or(body);
+    let (line, fields, error) = match parser.parse_located(body) {
+        Ok((line, fields)) => (line, fields, None),
+        Err(err) => (Line::Unknown(body), Vec::new(), Some(err)),
+    };
+
+    ParsedLine {
+        len: text.len(),
+        context,
+        line: line.map_str(&mut |s| Span::locate(body, s)),
+        fields,
+        error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Document, Edit, Item};
+    use crate::{
+        map::{Line, SectionName},
+        map_file::map_file,
+        span::FieldKind,
+        utils::test_utils::assert_diff,
+    };
+    use nom_supreme::error::ErrorTree;
+
+    #[test]
+    fn test_document() {
+        let input = "\
+.init section layout\r\n\
+\x20 00000000 0000f0 80003100  4 __start\x20\t__start.c.o \r\n\
+\r\n\
+Memory map:\r\n\
+\x20           .init  80003100 000000f0 000001c0\r\n\
+";
+        let mut document = Document::new(input.to_owned());
+        assert_eq!(document.len(), 5);
+        assert!(document.errors().is_empty());
+
+        let edit = |document: &mut Document, from: &str, to: &str| {
+            let start = document.text().find(from).unwrap();
+            document.edit(&Edit {
+                range: start..start + from.len(),
+                text: to.to_owned(),
+            })
+        };
+        let assert_parsed = |document: &Document| {
+            let expected = map_file::<ErrorTree<&str>>(document.text());
+            let expected = expected.unwrap().map_str(&mut str::to_owned);
+            assert_diff(&expected, document.map_file());
+        };
+        let virt_addr = |items: &[Item<String>]| match items {
+            [Item::SectionSymbol { symbol, .. }] => symbol.virt_addr,
+            items => panic!("Expected one symbol: {items:?}"),
+        };
+
+        // Editing a field only re-parses its own line.
+        let change = edit(&mut document, "80003100  4", "80003200  4");
+        assert_eq!((change.old, change.new), (1..2, 1..2));
+        assert_eq!(virt_addr(&change.changed), 0x80003200);
+        assert_eq!(virt_addr(&change.removed), 0x80003100);
+        assert_parsed(&document);
+
+        // Breaking a row keeps the rest of the document.
+        edit(&mut document, "0000f0", "0000fg");
+        assert_eq!(document.errors().len(), 1);
+        assert_eq!(document.errors()[0].line(), 2);
+        edit(&mut document, "0000fg", "0000f0");
+        assert!(document.errors().is_empty());
+
+        // Splitting a line in two.
+        let change = edit(&mut document, "\r\nMemory", "\r\n\r\nMemory");
+        assert_eq!((change.old, change.new), (2..4, 2..5));
+        assert!(change.changed.is_empty() && change.removed.is_empty());
+        assert_parsed(&document);
+
+        // Editing a title re-parses the rows of its table.
+        let change = edit(&mut document, "Memory map:", "Memory map");
+        assert_eq!(change.old, 4..6);
+        assert_eq!(document.errors().len(), 2);
+        assert!(matches!(change.removed[..], [Item::MemoryEntry(_)]));
+        assert_eq!(
+            change.changed,
+            [
+                Item::Unknown("Memory map".to_owned()),
+                Item::Unknown(
+                    "            .init  80003100 000000f0 000001c0".to_owned()
+                ),
+            ]
+        );
+        let numbers = document.map_file().unknown.iter().map(|(n, _)| *n);
+        assert_eq!(numbers.collect::<Vec<_>>(), [4, 5]);
+        edit(&mut document, "Memory map", "Memory map:");
+        assert!(document.errors().is_empty());
+        assert_parsed(&document);
+
+        // Joining lines, which also drops the rows after it out of the
+        // section table.
+        let change = edit(&mut document, "layout\r\n", "layout");
+        assert_eq!((change.old, change.new), (0..5, 0..4));
+        assert_eq!(virt_addr(&change.removed), 0x80003200);
+        assert!(matches!(document.line(0), Some(Line::Unknown(_))));
+        edit(&mut document, "layout", "layout\r\n");
+        assert_eq!(
+            document.line(0),
+            Some(Line::SectionTitle(SectionName::Init))
+        );
+        assert_parsed(&document);
+
+        // Appending a table only adds its rows.
+        let end = document.text().len();
+        let change = document.edit(&Edit {
+            range: end..end,
+            text: "\r\nLinker generated symbols:\r\n\
+                   \x20          _db_stack_addr 804f0c00\r\n"
+                .to_owned(),
+        });
+        assert!(matches!(change.changed[..], [Item::LinkerEntry(_)]));
+        assert!(change.removed.is_empty());
+        assert_parsed(&document);
+
+        let start = document.text().find("__start").unwrap();
+        assert_eq!(document.line_at(start), 1);
+        assert_eq!(document.line_start(1), Some(22));
+        let located = document.located_line(1).unwrap();
+        let identifier = located
+            .fields
+            .iter()
+            .find(|field| field.kind == FieldKind::Identifier)
+            .unwrap();
+        assert_eq!(identifier.span.offset, start);
+    }
+}
@@ -0,0 +1,486 @@
+use crate::{
+    error::Error,
+    map::{Identifier, SectionName},
+    map_file::MapFile,
+    memory_table, section_table,
+    windows::is_filename,
+};
+use nom::{
+    bytes::complete::take,
+    combinator::map,
+    error::ParseError,
+    multi::count,
+    number::complete::be_u32,
+    sequence::{terminated, tuple},
+    IResult,
+};
+use std::{
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+pub const HEADER_SIZE: usize = 0x100;
+pub const TEXT_COUNT: usize = 7;
+pub const DATA_COUNT: usize = 11;
+
+#[derive(Debug, Default, Eq, PartialEq, Clone, Copy, Hash)]
+pub struct Section {
+    pub offset: u32,
+    pub addr: u32,
+    pub size: u32,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+pub struct Header {
+    pub text: [Section; TEXT_COUNT],
+    pub data: [Section; DATA_COUNT],
+    pub bss_addr: u32,
+    pub bss_size: u32,
+    pub entry_point: u32,
+}
+
+/// A difference between a DOL header and a parsed map.
+#[derive(Debug, Eq, PartialEq, Hash)]
+pub enum Mismatch<'a, S: Eq + PartialEq> {
+    /// A loaded section of the memory map has no DOL section at its address.
+    MissingSection {
+        name: &'a SectionName<S>,
+        virt_addr: u32,
+    },
+    /// A DOL section is not described by the memory map.
+    UnmappedSection { section: Section },
+    /// A DOL section differs in size from its memory map entry.
+    Size {
+        name: &'a SectionName<S>,
+        map: u32,
+        dol: u32,
+    },
+    /// A DOL section differs in file offset from its memory map entry. Only
+    /// reported with [`Offsets::Compare`].
+    FileOffset {
+        name: &'a SectionName<S>,
+        map: u32,
+        dol: u32,
+    },
+    /// A zero-initialized section lies outside of the DOL's BSS range.
+    Bss {
+        name: &'a SectionName<S>,
+        virt_addr: u32,
+        size: u32,
+    },
+    /// The entry point differs from the address of the closure tree root.
+    EntryPoint { map: Option<u32>, dol: u32 },
+}
+
+impl Header {
+    /// Parses the header at the start of a DOL.
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        header::<nom::error::Error<&[u8]>>(data)
+            .map(|(_, header)| header)
+            .map_err(|_| {
+                Error::binary(
+                    data.len(),
+                    format!("expected a DOL header of {HEADER_SIZE} bytes"),
+                )
+            })
+    }
+
+    /// Iterates over the text and data sections that are present.
+    pub fn sections(&self) -> impl Iterator<Item = &Section> {
+        self.text
+            .iter()
+            .chain(self.data.iter())
+            .filter(|section| section.size != 0)
+    }
+
+    /// Compares this header against the memory map and the closure tree root
+    /// (usually `__start`) of a parsed map.
+    ///
+    /// File offsets are not compared, see [`Offsets::Ignore`].
+    pub fn verify<'a, S>(
+        &self,
+        map_file: &'a MapFile<S>,
+    ) -> Vec<Mismatch<'a, S>>
+    where
+        S: Eq + PartialEq + AsRef<str>,
+    {
+        self.verify_with(map_file, Offsets::Ignore)
+    }
+
+    /// Like [`Header::verify`], but also compares file offsets if `offsets`
+    /// is [`Offsets::Compare`].
+    pub fn verify_with<'a, S>(
+        &self,
+        map_file: &'a MapFile<S>,
+        offsets: Offsets,
+    ) -> Vec<Mismatch<'a, S>>
+    where
+        S: Eq + PartialEq + AsRef<str>,
+    {
+        let mut mismatches = Vec::new();
+        let mut mapped = Vec::new();
+
+        for entry in &map_file.memory {
+            let (name, virt_addr) = match &entry.data {
+                memory_table::Data::Main { name, virt_addr } => {
+                    (name, *virt_addr)
+                }
+                memory_table::Data::Debug { .. } => continue,
+            };
+            if entry.size == 0 {
+                continue;
+            }
+
+            if name.is_zero_initialized() {
+                let start = self.bss_addr as u64;
+                let end = start + self.bss_size as u64;
+                let addr = virt_addr as u64;
+                if addr < start || addr + entry.size as u64 > end {
+                    mismatches.push(Mismatch::Bss {
+                        name,
+                        virt_addr,
+                        size: entry.size,
+                    });
+                }
+                continue;
+            }
+
+            match self.sections().find(|section| section.addr == virt_addr) {
+                Some(section) => {
+                    mapped.push(*section);
+                    if section.size != entry.size {
+                        mismatches.push(Mismatch::Size {
+                            name,
+                            map: entry.size,
+                            dol: section.size,
+                        });
+                    }
+                    if offsets == Offsets::Compare
+                        && section.offset != entry.file_addr
+                    {
+                        mismatches.push(Mismatch::FileOffset {
+                            name,
+                            map: entry.file_addr,
+                            dol: section.offset,
+                        });
+                    }
+                }
+                None => mismatches
+                    .push(Mismatch::MissingSection { name, virt_addr }),
+            }
+        }
+
+        mismatches.extend(
+            self.sections()
+                .filter(|section| !mapped.contains(section))
+                .map(|section| Mismatch::UnmappedSection {
+                    section: *section,
+                }),
+        );
+
+        let entry_point = map_file.trees.first().and_then(|tree| {
+            map_file.symbols().find_map(|(_, symbol)| match symbol {
+                section_table::Symbol {
+                    data: section_table::Data::Parent { .. },
+                    id: Identifier::Named { name, .. },
+                    virt_addr,
+                    ..
+                } if name.as_ref() == tree.root.as_ref() => Some(*virt_addr),
+                _ => None,
+            })
+        });
+        if entry_point != Some(self.entry_point) {
+            mismatches.push(Mismatch::EntryPoint {
+                map: entry_point,
+                dol: self.entry_point,
+            });
+        }
+
+        mismatches
+    }
+}
+
+/// Whether [`Header::verify_with`] compares the file offsets of the memory
+/// map against those of the DOL.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Copy, Hash)]
+pub enum Offsets {
+    /// Skip them, as those of most maps refer to the intermediate ELF rather
+    /// than to the DOL.
+    #[default]
+    Ignore,
+    /// Compare them, for maps whose offsets refer to the DOL.
+    Compare,
+}
+
+/// How [`Dol::dump`] splits a DOL into files.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+pub enum Split {
+    /// One file per symbol in an executable section layout.
+    Functions,
+    /// One file per object file contribution to each section.
+    Objects,
+}
+
+/// A whole DOL executable along with its parsed header.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+pub struct Dol<'a> {
+    pub header: Header,
+    pub data: &'a [u8],
+}
+
+impl<'a> Dol<'a> {
+    /// Parses the header of a DOL, keeping the whole of `data` so that
+    /// symbols can be read from it.
+    pub fn parse(data: &'a [u8]) -> Result<Self, Error> {
+        Header::parse(data).map(|header| Self { header, data })
+    }
+
+    /// Reads `size` bytes starting at a virtual address, provided they lie
+    /// within a single text or data section.
+    pub fn read(&self, virt_addr: u32, size: u32) -> Option<&'a [u8]> {
+        let section = self.header.sections().find(|section| {
+            virt_addr >= section.addr
+                && virt_addr as u64 + size as u64
+                    <= section.addr as u64 + section.size as u64
+        })?;
+        let start =
+            section.offset.checked_add(virt_addr - section.addr)? as usize;
+
+        self.data.get(start..start.checked_add(size as usize)?)
+    }
+
+    /// Reads the bytes of a section layout symbol. Only symbols that have a
+    /// size, i.e. not `(entry of ...)` children, can be read.
+    pub fn symbol<S>(
+        &self,
+        symbol: &section_table::Symbol<S>,
+    ) -> Option<&'a [u8]>
+    where
+        S: Eq + PartialEq,
+    {
+        match symbol.data {
+            section_table::Data::Parent { size, .. } => {
+                self.read(symbol.virt_addr, size)
+            }
+            section_table::Data::Child { .. } => None,
+        }
+    }
+
+    /// Writes the bytes of every function or every object file contribution
+    /// into separate files in `dir`, returning the paths that were written.
+    ///
+    /// Files are named after the virtual address and the symbol or object
+    /// name, so that local symbols sharing a name do not collide.
+    pub fn dump<S>(
+        &self,
+        map_file: &MapFile<S>,
+        split: Split,
+        dir: &Path,
+    ) -> io::Result<Vec<PathBuf>>
+    where
+        S: Eq + PartialEq + Display,
+    {
+        let mut paths = Vec::new();
+
+        for (section, symbol) in map_file.symbols() {
+            let name = match (split, &symbol.id) {
+                (Split::Functions, Identifier::Section { .. }) => continue,
+                (Split::Functions, id) if section.is_executable() => {
+                    id.to_string()
+                }
+                (Split::Objects, Identifier::Section { idx: None, .. }) => {
+                    format!("{}_{}", section, symbol.origin.obj)
+                }
+                _ => continue,
+            };
+            let bytes = match self.symbol(symbol) {
+                Some(bytes) if !bytes.is_empty() => bytes,
+                _ => continue,
+            };
+
+            let name: String = name
+                .chars()
+                .map(|c| if is_filename(c) { c } else { '_' })
+                .collect();
+            let path =
+                dir.join(format!("{:08x}_{name}.bin", symbol.virt_addr));
+            fs::write(&path, bytes)?;
+            paths.push(path);
+        }
+
+        Ok(paths)
+    }
+}
+
+pub(crate) fn header<'a, E>(input: &'a [u8]) -> IResult<&'a [u8], Header, E>
+where
+    E: ParseError<&'a [u8]>,
+{
+    map(
+        terminated(
+            tuple((
+                count(be_u32, TEXT_COUNT),
+                count(be_u32, DATA_COUNT),
+                count(be_u32, TEXT_COUNT),
+                count(be_u32, DATA_COUNT),
+                count(be_u32, TEXT_COUNT),
+                count(be_u32, DATA_COUNT),
+                be_u32,
+                be_u32,
+                be_u32,
+            )),
+            take(0x1Cusize),
+        ),
+        |(
+            text_offsets,
+            data_offsets,
+            text_addrs,
+            data_addrs,
+            text_sizes,
+            data_sizes,
+            bss_addr,
+            bss_size,
+            entry_point,
+        )| {
+            let mut text = [Section::default(); TEXT_COUNT];
+            let mut data = [Section::default(); DATA_COUNT];
+            for (i, section) in text.iter_mut().enumerate() {
+                *section = Section {
+                    offset: text_offsets[i],
+                    addr: text_addrs[i],
+                    size: text_sizes[i],
+                };
+            }
+            for (i, section) in data.iter_mut().enumerate() {
+                *section = Section {
+                    offset: data_offsets[i],
+                    addr: data_addrs[i],
+                    size: data_sizes[i],
+                };
+            }
+
+            Header {
+                text,
+                data,
+                bss_addr,
+                bss_size,
+                entry_point,
+            }
+        },
+    )(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Dol, Header, Mismatch, Offsets, Section, Split, HEADER_SIZE};
+    use crate::{map::SectionName, parse_map};
+    use std::fs;
+
+    const MAP: &str = "\
+Link map of __start\r\n\
+\x20 1] __start (func,global) found in __start.c.o \r\n\
+\r\n\
+.init section layout\r\n\
+\x20 00000000 0000f0 80003100  1 .init\x20\t__start.c.o \r\n\
+\x20 00000000 0000f0 80003100  4 __start\x20\t__start.c.o \r\n\
+\r\n\
+Memory map:\r\n\
+\x20           .init  80003100 000000f0 00000100\r\n\
+\x20           .text  80003200 00000200 00000200\r\n\
+\x20            .bss  80004000 00000100 00000400\r\n\
+";
+
+    fn dol() -> Vec<u8> {
+        let mut dol = vec![0; HEADER_SIZE];
+        let mut put = |offset: usize, value: u32| {
+            dol[offset..offset + 4].copy_from_slice(&value.to_be_bytes())
+        };
+        put(0x00, 0x100);
+        put(0x04, 0x200);
+        put(0x48, 0x80003100);
+        put(0x4C, 0x80003200);
+        put(0x90, 0xf0);
+        put(0x94, 0x180);
+        put(0xD8, 0x80004000);
+        put(0xDC, 0x200);
+        put(0xE0, 0x80003100);
+        dol.extend((0..0x280).map(|i| i as u8));
+        dol
+    }
+
+    #[test]
+    fn test_verify() {
+        let map = parse_map(MAP).unwrap();
+        let header = Header::parse(&dol()).unwrap();
+        let err = Header::parse(&dol()[..HEADER_SIZE - 1]).unwrap_err();
+        assert_eq!(err.offset(), HEADER_SIZE - 1);
+
+        assert_eq!(
+            header.text[1],
+            Section {
+                offset: 0x200,
+                addr: 0x80003200,
+                size: 0x180,
+            }
+        );
+        assert_eq!(
+            header.verify(&map),
+            vec![Mismatch::Size {
+                name: &SectionName::Text,
+                map: 0x200,
+                dol: 0x180,
+            }]
+        );
+
+        let mut moved = header;
+        moved.text[1].offset = 0x300;
+        assert_eq!(moved.verify(&map), header.verify(&map));
+        assert_eq!(
+            moved.verify_with(&map, Offsets::Compare),
+            vec![
+                Mismatch::Size {
+                    name: &SectionName::Text,
+                    map: 0x200,
+                    dol: 0x180,
+                },
+                Mismatch::FileOffset {
+                    name: &SectionName::Text,
+                    map: 0x200,
+                    dol: 0x300,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract() {
+        let map = parse_map(MAP).unwrap();
+        let data = dol();
+        let dol = Dol::parse(&data).unwrap();
+
+        let (_, start) = map.symbols().nth(1).unwrap();
+        assert_eq!(dol.symbol(start), Some(&data[0x100..0x1f0]));
+        assert_eq!(dol.read(0x80003204, 4), Some(&data[0x204..0x208]));
+        assert_eq!(dol.read(0x800031f0, 0x20), None);
+
+        let mut header = dol.header;
+        header.text[0].offset = u32::MAX;
+        let malformed = super::Dol {
+            header,
+            data: &data,
+        };
+        assert_eq!(malformed.read(0x80003104, 4), None);
+
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        for (split, name) in [
+            (Split::Functions, "80003100___start.bin"),
+            (Split::Objects, "80003100_.init___start.c.o.bin"),
+        ] {
+            let paths = dol.dump(&map, split, dir).unwrap();
+            assert_eq!(paths, vec![dir.join(name)]);
+            assert_eq!(fs::read(&paths[0]).unwrap(), &data[0x100..0x1f0]);
+        }
+    }
+}
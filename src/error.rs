@@ -0,0 +1,280 @@
+use nom::error::ErrorKind;
+use nom_supreme::error::{BaseErrorKind, ErrorTree, StackContext};
+use std::fmt::{self, Display, Formatter};
+
+/// An error from one of the parsing functions at the crate root, pointing
+/// at the position in the input where parsing failed.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct Error {
+    offset: usize,
+    line: usize,
+    column: usize,
+    message: String,
+    attempted: Vec<&'static str>,
+}
+
+impl Error {
+    pub(crate) fn new(input: &str, offset: usize, message: String) -> Self {
+        let offset = offset.min(input.len());
+        let before = &input[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+        Self {
+            offset,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            message,
+            attempted: Vec::new(),
+        }
+    }
+
+    /// An error in binary input, e.g. a DOL, which has no lines, so the whole
+    /// input counts as the first line and columns count bytes.
+    pub(crate) fn binary(offset: usize, message: String) -> Self {
+        Self {
+            offset,
+            line: 1,
+            column: offset + 1,
+            message,
+            attempted: Vec::new(),
+        }
+    }
+
+    /// Converts an error from the nom parsers, reporting the branch that got
+    /// the furthest into the input along with the field and the kinds of row
+    /// that were being parsed.
+    pub(crate) fn from_nom(
+        input: &str,
+        err: nom::Err<ErrorTree<&str>>,
+    ) -> Self {
+        let tree = match err {
+            nom::Err::Error(tree) | nom::Err::Failure(tree) => tree,
+            nom::Err::Incomplete(_) => {
+                return Self::new(
+                    input,
+                    input.len(),
+                    "unexpected end of input".to_owned(),
+                )
+            }
+        };
+
+        let mut leaves = Vec::new();
+        collect_leaves(&tree, &mut Vec::new(), &mut leaves);
+        let Some(leaf) = leaves
+            .iter()
+            .rev()
+            .max_by_key(|leaf| offset(input, leaf.location))
+        else {
+            return Self::new(input, 0, "failed to parse".to_owned());
+        };
+
+        let mut message = match leaf.kind {
+            BaseErrorKind::Expected(expectation) => {
+                format!("expected {expectation}")
+            }
+            BaseErrorKind::Kind(ErrorKind::Tag) => {
+                "unexpected text".to_owned()
+            }
+            BaseErrorKind::Kind(ErrorKind::Eof) => {
+                "expected end of line".to_owned()
+            }
+            BaseErrorKind::Kind(kind) => {
+                format!("unexpected input ({})", kind.description())
+            }
+            BaseErrorKind::External(err) => err.to_string(),
+        };
+        // The outermost context names the row, any others name its fields.
+        if let Some(field) = leaf.contexts.iter().skip(1).last() {
+            message = format!("{message} for {field}");
+        }
+
+        let mut attempted = Vec::new();
+        for row in leaves.iter().filter_map(|leaf| leaf.contexts.first()) {
+            if !attempted.contains(row) {
+                attempted.push(*row);
+            }
+        }
+
+        Self {
+            attempted,
+            ..Self::new(input, offset(input, leaf.location), message)
+        }
+    }
+
+    /// Moves an error from a slice of `input` starting at `start` to `input`
+    /// itself.
+    pub(crate) fn relocate(&self, input: &str, start: usize) -> Self {
+        Self {
+            attempted: self.attempted.clone(),
+            ..Self::new(input, start + self.offset, self.message.clone())
+        }
+    }
+
+    /// The byte offset into the input.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The 1-based line number.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The 1-based column, counted in characters.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The kinds of row that the line was tried as, e.g. `memory map entry`.
+    pub fn attempted(&self) -> &[&'static str] {
+        &self.attempted
+    }
+
+    /// Renders the error along with the line of `input` it points at, where
+    /// `input` is the text that was parsed.
+    ///
+    /// ```text
+    /// error: expected 8 hex digits for virtual address
+    ///  --> 3:20
+    ///   |
+    /// 3 |             .text  8000310 000000f0 000001c0
+    ///   |                    ^
+    ///   = attempted: memory map entry
+    /// ```
+    pub fn report<'a>(&'a self, input: &'a str) -> Report<'a> {
+        Report { error: self, input }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// An [`Error`] rendered with a snippet of the input, see [`Error::report`].
+#[derive(Debug, Clone, Copy)]
+pub struct Report<'a> {
+    error: &'a Error,
+    input: &'a str,
+}
+
+impl Display for Report<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let Report { error, input } = *self;
+        let number = error.line.to_string();
+        let gutter = " ".repeat(number.len());
+        let text = input.lines().nth(error.line - 1).unwrap_or_default();
+        // Keep any tabs so that the caret lines up with the text above it.
+        let indent = text
+            .chars()
+            .take(error.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+
+        writeln!(f, "error: {}", error.message)?;
+        writeln!(f, "{gutter}--> {}:{}", error.line, error.column)?;
+        writeln!(f, "{gutter} |")?;
+        writeln!(f, "{number} | {text}")?;
+        write!(f, "{gutter} | {indent}^")?;
+        if !error.attempted.is_empty() {
+            write!(
+                f,
+                "\n{gutter} = attempted: {}",
+                error.attempted.join(", ")
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+struct Leaf<'t, 'a> {
+    location: &'a str,
+    kind: &'t BaseErrorKind<
+        &'static str,
+        Box<dyn std::error::Error + Send + Sync>,
+    >,
+    /// From outermost to innermost.
+    contexts: Vec<&'static str>,
+}
+
+fn collect_leaves<'t, 'a>(
+    tree: &'t ErrorTree<&'a str>,
+    contexts: &mut Vec<&'static str>,
+    leaves: &mut Vec<Leaf<'t, 'a>>,
+) {
+    match tree {
+        ErrorTree::Base { location, kind } => leaves.push(Leaf {
+            location,
+            kind,
+            contexts: contexts.clone(),
+        }),
+        ErrorTree::Stack {
+            base,
+            contexts: stack,
+        } => {
+            let len = contexts.len();
+            // The stack is ordered from innermost to outermost.
+            contexts.extend(stack.iter().rev().filter_map(|(_, context)| {
+                match context {
+                    StackContext::Context(context) => Some(*context),
+                    StackContext::Kind(_) => None,
+                }
+            }));
+            collect_leaves(base, contexts, leaves);
+            contexts.truncate(len);
+        }
+        ErrorTree::Alt(trees) => {
+            for tree in trees {
+                collect_leaves(tree, contexts, leaves);
+            }
+        }
+    }
+}
+
+/// The offset of `part` within `input`, if it is a slice of it.
+pub(crate) fn offset(input: &str, part: &str) -> usize {
+    let start = input.as_ptr() as usize;
+    let part = part.as_ptr() as usize;
+
+    match part.checked_sub(start) {
+        Some(offset) if offset <= input.len() => offset,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse_map;
+
+    #[test]
+    fn test_error() {
+        let input = "\
+Memory map:\r\n\
+\x20           .init  80003100 000000f0 000001c0\r\n\
+\x20           .text  8000310 000000f0 000001c0\r\n\
+";
+
+        let err = parse_map(input).unwrap_err();
+        assert_eq!(err.line(), 3);
+        assert_eq!(err.column(), 20);
+        assert_eq!(err.to_string(), format!("3:20: {}", err.message()));
+        assert_eq!(
+            err.report(input).to_string(),
+            "\
+error: expected 8 hex digits for virtual address
+\x20--> 3:20
+\x20 |
+3 |             .text  8000310 000000f0 000001c0
+\x20 |                    ^
+\x20 = attempted: memory map entry"
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! Tabular export of section layouts, e.g. for loading into a spreadsheet.
+//!
+//! Each section layout symbol becomes a row. Offsets and addresses are
+//! written in hex with a `0x` prefix, while sizes and alignments are written
+//! in decimal so that they can be summed.
+
+use crate::{
+    demangle::demangle, map::Identifier, map_file::MapFile, section_table,
+    tree,
+};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    hash::Hash,
+    io::{self, Write},
+};
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+pub enum Format {
+    /// Comma separated values, quoted as in RFC 4180.
+    Csv,
+    /// Tab separated values.
+    Tsv,
+}
+
+impl Format {
+    fn delimiter(self) -> char {
+        match self {
+            Format::Csv => ',',
+            Format::Tsv => '\t',
+        }
+    }
+}
+
+const COLUMNS: [&str; 12] = [
+    "section",
+    "offset",
+    "size",
+    "virt_addr",
+    "align",
+    "kind",
+    "name",
+    "demangled",
+    "object",
+    "source",
+    "asm",
+    "parent",
+];
+
+/// The extra columns written when the map has closure trees.
+const TREE_COLUMNS: [&str; 2] = ["type", "scope"];
+
+/// Writes a header row followed by a row for every section layout symbol.
+///
+/// The `type` and `scope` columns are only written if the map has closure
+/// trees, and are left empty for symbols that no tree mentions.
+pub fn write_symbols<S, W>(
+    mut writer: W,
+    map_file: &MapFile<S>,
+    format: Format,
+) -> io::Result<()>
+where
+    S: Eq + PartialEq + Hash + Display,
+    W: Write,
+{
+    let delimiter = format.delimiter();
+    let has_trees = !map_file.trees.is_empty();
+    let specifiers = specifiers(map_file);
+
+    let mut header = COLUMNS.to_vec();
+    if has_trees {
+        header.extend(TREE_COLUMNS);
+    }
+    write_row(&mut writer, delimiter, header)?;
+
+    for (section, symbol) in map_file.symbols() {
+        let (size, align, parent) = match &symbol.data {
+            section_table::Data::Parent { size, align } => {
+                (size.to_string(), align.to_string(), String::new())
+            }
+            section_table::Data::Child { parent } => {
+                (String::new(), String::new(), parent.to_string())
+            }
+        };
+        let name = symbol.id.to_string();
+
+        let mut row = vec![
+            section.to_string(),
+            format!("{:#x}", symbol.addr),
+            size,
+            format!("{:#x}", symbol.virt_addr),
+            align,
+            symbol.id.kind().to_owned(),
+            name.clone(),
+            demangle(&name).unwrap_or_default(),
+            symbol.origin.obj.to_string(),
+            symbol
+                .origin
+                .src
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_default(),
+            symbol.origin.asm.to_string(),
+            parent,
+        ];
+        if has_trees {
+            let specifier = specifiers
+                .get(&(&symbol.id, Some(&symbol.origin.obj)))
+                .or_else(|| specifiers.get(&(&symbol.id, None)));
+            row.push(
+                specifier
+                    .map(|specifier| specifier.r#type.to_string())
+                    .unwrap_or_default(),
+            );
+            row.push(
+                specifier
+                    .map(|specifier| specifier.scope.to_string())
+                    .unwrap_or_default(),
+            );
+        }
+        write_row(&mut writer, delimiter, row)?;
+    }
+
+    Ok(())
+}
+
+/// The first specifier of each symbol in the closure trees, keyed by its
+/// identifier along with its object and also by its identifier alone.
+fn specifiers<S>(
+    map_file: &MapFile<S>,
+) -> HashMap<(&Identifier<S>, Option<&S>), &tree::Specifier<S>>
+where
+    S: Eq + PartialEq + Hash,
+{
+    let mut specifiers = HashMap::new();
+    for node in map_file.trees.iter().flat_map(|tree| &tree.nodes) {
+        if let tree::Data::Object(id, specifier) = &node.data {
+            specifiers
+                .entry((id, Some(&specifier.origin.obj)))
+                .or_insert(specifier);
+            specifiers.entry((id, None)).or_insert(specifier);
+        }
+    }
+    specifiers
+}
+
+fn write_row<W, T>(
+    writer: &mut W,
+    delimiter: char,
+    fields: impl IntoIterator<Item = T>,
+) -> io::Result<()>
+where
+    W: Write,
+    T: AsRef<str>,
+{
+    for (i, field) in fields.into_iter().enumerate() {
+        if i > 0 {
+            write!(writer, "{delimiter}")?;
+        }
+        let field = field.as_ref();
+        if field.contains([delimiter, '"', '\r', '\n']) {
+            write!(writer, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            writer.write_all(field.as_bytes())?;
+        }
+    }
+    writer.write_all(b"\r\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_symbols, Format};
+    use crate::{parse_map, utils::test_utils::assert_diff};
+    use test_case::test_case;
+
+    const INPUT: &str = "\
+.text section layout\r\n\
+\x20 00000000 00002c 80003100  4 __ct__Q24Util6VectorFfff\x20\tKyoto.a Vector.cpp\r\n\
+\x20 00000030 000010 80003130  4 memset\x20\tmem.a mem.c\r\n\
+\x20 00000030 000000 80003130 __fill_mem (entry of memset) \tmem.a mem.c\r\n\
+";
+
+    #[test_case(Format::Csv, "\
+section,offset,size,virt_addr,align,kind,name,demangled,object,source,asm,parent\r\n\
+.text,0x0,44,0x80003100,4,named,__ct__Q24Util6VectorFfff,\"Util::Vector::Vector(float, float, float)\",Kyoto.a,Vector.cpp,false,\r\n\
+.text,0x30,16,0x80003130,4,named,memset,,mem.a,mem.c,false,\r\n\
+.text,0x30,,0x80003130,,named,__fill_mem,,mem.a,mem.c,false,memset\r\n\
+" ; "csv")]
+    #[test_case(Format::Tsv, "\
+section\toffset\tsize\tvirt_addr\talign\tkind\tname\tdemangled\tobject\tsource\tasm\tparent\r\n\
+.text\t0x0\t44\t0x80003100\t4\tnamed\t__ct__Q24Util6VectorFfff\tUtil::Vector::Vector(float, float, float)\tKyoto.a\tVector.cpp\tfalse\t\r\n\
+.text\t0x30\t16\t0x80003130\t4\tnamed\tmemset\t\tmem.a\tmem.c\tfalse\t\r\n\
+.text\t0x30\t\t0x80003130\t\tnamed\t__fill_mem\t\tmem.a\tmem.c\tfalse\tmemset\r\n\
+" ; "tsv")]
+    fn test_write_symbols(format: Format, expected: &str) {
+        let mut buf = Vec::new();
+        write_symbols(&mut buf, &parse_map(INPUT).unwrap(), format).unwrap();
+        assert_diff(&expected, &String::from_utf8(buf).unwrap().as_str());
+    }
+
+    #[test]
+    fn test_write_symbols_with_trees() {
+        let input = format!(
+            "\
+Link map of __start\r\n\
+\x20 1] memset (func,global) found in mem.a mem.c\r\n\
+\r\n\
+{INPUT}"
+        );
+
+        let mut buf = Vec::new();
+        write_symbols(&mut buf, &parse_map(&input).unwrap(), Format::Csv)
+            .unwrap();
+        let output = String::from_utf8(buf).unwrap();
+        let rows = output.lines().collect::<Vec<_>>();
+        assert!(rows[0].ends_with(",parent,type,scope"));
+        assert!(rows[1].ends_with(",false,,,"));
+        assert!(rows[2].ends_with(",false,,func,global"));
+    }
+}
@@ -0,0 +1,394 @@
+//! A C API for tools written in other languages, built as a `cdylib` with
+//! `cargo rustc --lib --release --features ffi --crate-type cdylib`.
+//!
+//! The header is checked in as `include/cwparse.h` and regenerated with
+//! `cbindgen --output include/cwparse.h`. Every string and array returned
+//! through the API is owned by its [`CwMap`] and stays valid until the map is
+//! freed.
+
+use crate::{map_file::MapFile, memory_table, parse_map, section_table};
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    fs,
+    ptr::{self, null},
+    slice,
+};
+
+/// A row of a section layout.
+#[repr(C)]
+pub struct CwSymbol {
+    /// The address of the symbol relative to its section.
+    pub addr: u32,
+    pub virt_addr: u32,
+    /// Only valid if `has_file_addr` is set.
+    pub file_addr: u32,
+    pub has_file_addr: bool,
+    /// Zero for entry symbols, which are part of their parent symbol.
+    pub size: u32,
+    pub align: u8,
+    pub name: *const c_char,
+    /// The symbol that an entry symbol is part of, or null.
+    pub parent: *const c_char,
+    pub section: *const c_char,
+    pub object: *const c_char,
+    /// The source file within the object, or null.
+    pub source: *const c_char,
+}
+
+/// A row of the memory map.
+#[repr(C)]
+pub struct CwMemoryEntry {
+    pub name: *const c_char,
+    /// Only valid if `has_virt_addr` is set, which it is not for debug
+    /// sections.
+    pub virt_addr: u32,
+    pub has_virt_addr: bool,
+    pub size: u32,
+    pub file_addr: u32,
+    /// The ROM columns are only valid if `has_rom` is set.
+    pub has_rom: bool,
+    pub rom_addr: u32,
+    pub ram_buffer_addr: u32,
+    pub s_record_line: u32,
+    pub bin_file_addr: u32,
+    pub bin_file_name: *const c_char,
+}
+
+/// A row of the linker generated symbols table.
+#[repr(C)]
+pub struct CwLinkerEntry {
+    pub name: *const c_char,
+    pub virt_addr: u32,
+}
+
+/// A parsed map, which must be freed with `cw_map_free`.
+pub struct CwMap {
+    map_file: MapFile<String>,
+    symbols: Vec<CwSymbol>,
+    memory: Vec<CwMemoryEntry>,
+    linker: Vec<CwLinkerEntry>,
+    /// Owns the strings pointed to by the rows.
+    _strings: Vec<CString>,
+}
+
+impl CwMap {
+    fn new(map_file: MapFile<String>) -> Self {
+        let mut strings = Vec::new();
+        let mut string = |s: String| {
+            let s = CString::new(s).unwrap_or_default();
+            let ptr = s.as_ptr();
+            strings.push(s);
+            ptr
+        };
+
+        let symbols = map_file
+            .symbols()
+            .map(|(section, symbol)| {
+                let (size, align, parent) = match &symbol.data {
+                    section_table::Data::Parent { size, align } => {
+                        (*size, *align, null())
+                    }
+                    section_table::Data::Child { parent } => {
+                        (0, 0, string(parent.to_string()))
+                    }
+                };
+                CwSymbol {
+                    addr: symbol.addr,
+                    virt_addr: symbol.virt_addr,
+                    file_addr: symbol.file_addr.unwrap_or_default(),
+                    has_file_addr: symbol.file_addr.is_some(),
+                    size,
+                    align,
+                    name: string(symbol.id.to_string()),
+                    parent,
+                    section: string(section.to_string()),
+                    object: string(symbol.origin.obj.clone()),
+                    source: symbol
+                        .origin
+                        .src
+                        .clone()
+                        .map_or(null(), &mut string),
+                }
+            })
+            .collect();
+
+        let memory = map_file
+            .memory
+            .iter()
+            .map(|entry| {
+                let (name, virt_addr) = match &entry.data {
+                    memory_table::Data::Main { name, virt_addr } => {
+                        (name.to_string(), Some(*virt_addr))
+                    }
+                    memory_table::Data::Debug { name } => {
+                        (name.to_string(), None)
+                    }
+                };
+                let rom = entry.rom.as_ref();
+                CwMemoryEntry {
+                    name: string(name),
+                    virt_addr: virt_addr.unwrap_or_default(),
+                    has_virt_addr: virt_addr.is_some(),
+                    size: entry.size,
+                    file_addr: entry.file_addr,
+                    has_rom: rom.is_some(),
+                    rom_addr: rom.map_or(0, |rom| rom.rom_addr),
+                    ram_buffer_addr: rom.map_or(0, |rom| rom.ram_buffer_addr),
+                    s_record_line: rom.map_or(0, |rom| rom.s_record_line),
+                    bin_file_addr: rom.map_or(0, |rom| rom.bin_file_addr),
+                    bin_file_name: rom.map_or(null(), |rom| {
+                        string(rom.bin_file_name.clone())
+                    }),
+                }
+            })
+            .collect();
+
+        let linker = map_file
+            .linker
+            .iter()
+            .map(|entry| CwLinkerEntry {
+                name: string(entry.name.clone()),
+                virt_addr: entry.virt_addr,
+            })
+            .collect();
+
+        Self {
+            map_file,
+            symbols,
+            memory,
+            linker,
+            _strings: strings,
+        }
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    LAST_ERROR.with(|last| {
+        *last.borrow_mut() = Some(CString::new(message).unwrap_or_default())
+    });
+}
+
+fn parse(text: &str) -> *mut CwMap {
+    match parse_map(text) {
+        Ok(map_file) => {
+            let map_file = map_file.map_str(&mut str::to_owned);
+            Box::into_raw(Box::new(CwMap::new(map_file)))
+        }
+        Err(err) => {
+            set_last_error(err.report(text).to_string());
+            ptr::null_mut()
+        }
+    }
+}
+
+/// The message of the last error on this thread, or null. It stays valid
+/// until the next call that fails.
+#[no_mangle]
+pub extern "C" fn cw_last_error() -> *const c_char {
+    LAST_ERROR
+        .with(|last| last.borrow().as_ref().map_or(null(), |s| s.as_ptr()))
+}
+
+/// Parses the `len` bytes of UTF-8 at `text`, or returns null on failure,
+/// including when `text` is null.
+///
+/// # Safety
+///
+/// `text` must be null or point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn cw_map_parse(
+    text: *const c_char,
+    len: usize,
+) -> *mut CwMap {
+    if text.is_null() {
+        set_last_error("text is null".to_owned());
+        return ptr::null_mut();
+    }
+    let bytes = slice::from_raw_parts(text.cast::<u8>(), len);
+    match std::str::from_utf8(bytes) {
+        Ok(text) => parse(text),
+        Err(err) => {
+            set_last_error(err.to_string());
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Reads and parses the map at `path`, or returns null on failure,
+/// including when `path` is null.
+///
+/// # Safety
+///
+/// `path` must be null or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn cw_map_open(path: *const c_char) -> *mut CwMap {
+    if path.is_null() {
+        set_last_error("path is null".to_owned());
+        return ptr::null_mut();
+    }
+    let path = CStr::from_ptr(path).to_string_lossy();
+    match fs::read_to_string(path.as_ref()) {
+        Ok(text) => parse(&text),
+        Err(err) => {
+            set_last_error(format!("{path}: {err}"));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Frees a map returned by `cw_map_parse` or `cw_map_open`.
+///
+/// # Safety
+///
+/// `map` must be null or a map that has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn cw_map_free(map: *mut CwMap) {
+    if !map.is_null() {
+        drop(Box::from_raw(map));
+    }
+}
+
+/// The rows of every section layout, storing their number in `len`.
+///
+/// # Safety
+///
+/// `map` must be a valid map and `len` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn cw_map_symbols(
+    map: *const CwMap,
+    len: *mut usize,
+) -> *const CwSymbol {
+    let symbols = &(*map).symbols;
+    *len = symbols.len();
+    symbols.as_ptr()
+}
+
+/// The rows of the memory map, storing their number in `len`.
+///
+/// # Safety
+///
+/// `map` must be a valid map and `len` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn cw_map_memory_entries(
+    map: *const CwMap,
+    len: *mut usize,
+) -> *const CwMemoryEntry {
+    let memory = &(*map).memory;
+    *len = memory.len();
+    memory.as_ptr()
+}
+
+/// The rows of the linker generated symbols table, storing their number in
+/// `len`.
+///
+/// # Safety
+///
+/// `map` must be a valid map and `len` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn cw_map_linker_entries(
+    map: *const CwMap,
+    len: *mut usize,
+) -> *const CwLinkerEntry {
+    let linker = &(*map).linker;
+    *len = linker.len();
+    linker.as_ptr()
+}
+
+/// Finds the symbol whose data contains `virt_addr`, or returns null.
+///
+/// # Safety
+///
+/// `map` must be a valid map.
+#[no_mangle]
+pub unsafe extern "C" fn cw_map_lookup(
+    map: *const CwMap,
+    virt_addr: u32,
+) -> *const CwSymbol {
+    let map = &*map;
+    map.map_file
+        .symbol_at(virt_addr)
+        .and_then(|(_, found)| {
+            map.map_file
+                .symbols()
+                .position(|(_, symbol)| ptr::eq(symbol, found))
+        })
+        .map_or(null(), |index| &map.symbols[index])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        cw_last_error, cw_map_free, cw_map_linker_entries, cw_map_lookup,
+        cw_map_memory_entries, cw_map_parse, cw_map_symbols,
+    };
+    use std::ffi::CStr;
+
+    #[test]
+    fn test_header() {
+        assert_eq!(
+            include_str!("../include/cwparse.h"),
+            include_str!(concat!(env!("OUT_DIR"), "/cwparse.h")),
+            "include/cwparse.h is out of date, regenerate it with cbindgen"
+        );
+    }
+
+    #[test]
+    fn test_ffi() {
+        let input = "\
+.text section layout\r\n\
+\x20 00000000 000010 80003100  4 __start\x20\t__start.c.o \r\n\
+\x20 00000010 00002c 80003110  4 memset\x20\tmem.a mem.c\r\n\
+\x20 00000020 000000 80003120 __fill_mem (entry of memset) \tmem.a mem.c\r\n\
+\r\n\
+Memory map:\r\n\
+\x20           .text  80003100 000023a8 000001c0\r\n\
+\x20     .debug_info           000040 000002c0\r\n\
+\r\n\
+Linker generated symbols:\r\n\
+\x20              _stack_addr 80400000\r\n\
+";
+        let string = |s| unsafe { CStr::from_ptr(s) }.to_str().unwrap();
+
+        unsafe {
+            assert!(cw_map_parse(std::ptr::null(), 0).is_null());
+            assert_eq!(string(cw_last_error()), "text is null");
+
+            let map = cw_map_parse(input.as_ptr().cast(), input.len());
+            assert!(!map.is_null());
+
+            let mut len = 0;
+            let symbols = cw_map_symbols(map, &mut len);
+            assert_eq!(len, 3);
+            let symbols = std::slice::from_raw_parts(symbols, len);
+            assert_eq!(string(symbols[1].name), "memset");
+            assert_eq!(string(symbols[1].section), ".text");
+            assert_eq!(string(symbols[1].source), "mem.c");
+            assert_eq!(string(symbols[2].parent), "memset");
+            assert!(symbols[0].source.is_null());
+
+            let found = cw_map_lookup(map, 0x8000311c);
+            assert_eq!(string((*found).name), "memset");
+            assert!(cw_map_lookup(map, 0x90000000).is_null());
+
+            let memory = cw_map_memory_entries(map, &mut len);
+            let memory = std::slice::from_raw_parts(memory, len);
+            assert_eq!(string(memory[1].name), ".debug_info");
+            assert!(!memory[1].has_virt_addr);
+
+            let linker = cw_map_linker_entries(map, &mut len);
+            assert_eq!(len, 1);
+            assert_eq!((*linker).virt_addr, 0x80400000);
+
+            cw_map_free(map);
+
+            let map = cw_map_parse("Memory map:\nnope\n".as_ptr().cast(), 17);
+            assert!(map.is_null());
+            assert!(string(cw_last_error()).starts_with("error: "));
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use crate::{
+    error::Error,
+    linker_table,
+    map::Context,
+    map_file::{map_file, MapFile, Section, Tree},
+    memory_table,
+};
+use nom::error::{ContextError, FromExternalError, ParseError};
+use nom_supreme::error::ErrorTree;
+use std::{num::ParseIntError, ops::Range};
+
+/// The location of a single table within a map.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct Table<'a> {
+    /// The context right after the title, or [`Context::None`] for any text
+    /// before the first title. Rows are not scanned, so section layouts
+    /// always have the default [`Layout`](crate::section_table::Layout).
+    pub kind: Context,
+    /// The tree root or section name from the title, e.g. `__start` or
+    /// `.text`.
+    pub name: Option<&'a str>,
+    /// The byte range of the table, starting at its title.
+    pub range: Range<usize>,
+}
+
+/// The byte ranges of every table in a map, found by scanning for titles
+/// without parsing any rows, so that individual tables can be parsed on
+/// demand.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct MapIndex<'a> {
+    input: &'a str,
+    tables: Vec<Table<'a>>,
+}
+
+impl<'a> MapIndex<'a> {
+    pub fn new(input: &'a str) -> Self {
+        let mut tables = Vec::new();
+        let mut push = |table: Table<'a>| {
+            // Skip blank lines before the first title.
+            if table.kind != Context::None
+                || !input[table.range.clone()].trim().is_empty()
+            {
+                tables.push(table);
+            }
+        };
+        let mut table = Table {
+            kind: Context::None,
+            name: None,
+            range: 0..0,
+        };
+
+        for line in input.split_inclusive('\n') {
+            let title = line.trim_end_matches(['\r', '\n']);
+            let (kind, name) = if let Some(root) =
+                title.strip_prefix("Link map of ")
+            {
+                (Context::Tree, Some(root))
+            } else if let Some(name) = title.strip_suffix(" section layout") {
+                (Context::Section(Default::default()), Some(name))
+            } else if title == "Memory map:" {
+                (Context::Memory(Default::default()), None)
+            } else if title == "Linker generated symbols:" {
+                (Context::Linker, None)
+            } else if title == "Mixed Mode Islands" {
+                (Context::MixedModeIslands, None)
+            } else if title == "Branch Islands" {
+                (Context::BranchIslands, None)
+            } else {
+                table.range.end += line.len();
+                continue;
+            };
+
+            let start = table.range.end;
+            let next = Table {
+                kind,
+                name,
+                range: start..start + line.len(),
+            };
+            push(std::mem::replace(&mut table, next));
+        }
+        push(table);
+
+        Self { input, tables }
+    }
+
+    pub fn tables(&self) -> &[Table<'a>] {
+        &self.tables
+    }
+
+    /// The text of a table, including its title.
+    pub fn text(&self, table: &Table<'a>) -> &'a str {
+        &self.input[table.range.clone()]
+    }
+
+    /// Parses a single table into a map that contains only that table.
+    pub fn parse(&self, table: &Table<'a>) -> Result<MapFile<&'a str>, Error> {
+        self.map_file::<ErrorTree<&str>>(table)
+            .map_err(|err| Error::from_nom(self.input, err))
+    }
+
+    pub(crate) fn map_file<E>(
+        &self,
+        table: &Table<'a>,
+    ) -> Result<MapFile<&'a str>, nom::Err<E>>
+    where
+        E: ParseError<&'a str>
+            + FromExternalError<&'a str, ParseIntError>
+            + FromExternalError<&'a str, &'static str>
+            + ContextError<&'a str>,
+    {
+        map_file(self.text(table))
+    }
+
+    /// Parses the section layout with the given name, e.g. `.text`.
+    pub fn section(
+        &self,
+        name: &str,
+    ) -> Option<Result<Section<&'a str>, Error>> {
+        let table =
+            self.find(Context::Section(Default::default()), Some(name))?;
+        Some(self.parse(table).map(|mut map_file| {
+            map_file.sections.pop().expect("Section title was indexed")
+        }))
+    }
+
+    /// Parses the closure tree with the given root, e.g. `__start`.
+    pub fn tree(&self, root: &str) -> Option<Result<Tree<&'a str>, Error>> {
+        let table = self.find(Context::Tree, Some(root))?;
+        Some(self.parse(table).map(|mut map_file| {
+            map_file.trees.pop().expect("Tree title was indexed")
+        }))
+    }
+
+    pub fn memory(
+        &self,
+    ) -> Option<Result<Vec<memory_table::Entry<&'a str>>, Error>> {
+        let table = self.find(Context::Memory(Default::default()), None)?;
+        Some(self.parse(table).map(|map_file| map_file.memory))
+    }
+
+    pub fn linker(
+        &self,
+    ) -> Option<Result<Vec<linker_table::Entry<&'a str>>, Error>> {
+        let table = self.find(Context::Linker, None)?;
+        Some(self.parse(table).map(|map_file| map_file.linker))
+    }
+
+    fn find(&self, kind: Context, name: Option<&str>) -> Option<&Table<'a>> {
+        self.tables
+            .iter()
+            .find(|table| table.kind == kind && table.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MapIndex;
+    use crate::{
+        map::{Context, SectionName},
+        section_table::Layout,
+    };
+
+    #[test]
+    fn test_index() {
+        let input = "\
+Link map of __start\r\n\
+\x20 1] __start (func,global) found in __start.c.o \r\n\
+\r\n\
+.init section layout\r\n\
+\x20 00000000 0000f0 80003100  4 __start\x20\t__start.c.o \r\n\
+\r\n\
+.text section layout\r\n\
+\x20 00000000 000010 80003200  4 main\x20\tmain.c.o \r\n\
+\x20 this row is broken\r\n\
+\r\n\
+Memory map:\r\n\
+\x20           .init  80003100 000000f0 000001c0\r\n\
+";
+
+        let index = MapIndex::new(input);
+        let kinds = index
+            .tables()
+            .iter()
+            .map(|table| (table.kind, table.name))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                (Context::Tree, Some("__start")),
+                (Context::Section(Layout::Standard), Some(".init")),
+                (Context::Section(Layout::Standard), Some(".text")),
+                (Context::Memory(Default::default()), None),
+            ]
+        );
+        assert!(index.text(&index.tables()[1]).starts_with(".init"));
+
+        let init = index.section(".init").unwrap().unwrap();
+        assert_eq!(init.name, SectionName::Init);
+        assert_eq!(init.symbols.len(), 1);
+        assert!(index.section(".text").unwrap().is_err());
+        assert!(index.section(".data").is_none());
+        assert_eq!(index.memory().unwrap().unwrap().len(), 1);
+        assert!(index.linker().is_none());
+    }
+}
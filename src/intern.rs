@@ -0,0 +1,103 @@
+use std::{collections::HashMap, ops::Index, sync::Arc};
+
+/// A compact handle to a string interned in a [`StringPool`].
+///
+/// Handles from the same pool are equal exactly when their strings are.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Clone, Copy, Hash)]
+pub struct Handle(u32);
+
+impl Handle {
+    pub fn index(self) -> u32 {
+        self.0
+    }
+}
+
+/// Deduplicates the identifiers, object names and source names of parsed
+/// maps, so that each distinct string is stored only once.
+#[derive(Debug, Default, Clone)]
+pub struct StringPool {
+    strings: Vec<Arc<str>>,
+    handles: HashMap<Arc<str>, Handle>,
+}
+
+impl StringPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the handle of `s`, adding it to the pool if needed.
+    pub fn intern(&mut self, s: &str) -> Handle {
+        if let Some(handle) = self.handles.get(s) {
+            return *handle;
+        }
+
+        let handle = Handle(
+            u32::try_from(self.strings.len())
+                .expect("String pool has more than u32::MAX strings"),
+        );
+        let s: Arc<str> = Arc::from(s);
+        self.strings.push(s.clone());
+        self.handles.insert(s, handle);
+
+        handle
+    }
+
+    /// Returns the handle of `s` without adding it to the pool.
+    pub fn get(&self, s: &str) -> Option<Handle> {
+        self.handles.get(s).copied()
+    }
+
+    pub fn resolve(&self, handle: Handle) -> &str {
+        &self.strings[handle.0 as usize]
+    }
+
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
+impl Index<Handle> for StringPool {
+    type Output = str;
+
+    fn index(&self, handle: Handle) -> &str {
+        self.resolve(handle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StringPool;
+    use crate::{
+        map::{Identifier, SectionName},
+        parse_map_interned,
+    };
+
+    #[test]
+    fn test_intern() {
+        let input = "\
+.text section layout\r\n\
+\x20 00000000 000010 80003100  4 foo\x20\tmain.c.o \r\n\
+\x20 00000010 000010 80003110  4 bar\x20\tmain.c.o \r\n\
+\x20 00000020 000010 80003120  4 foo\x20\tother.c.o \r\n\
+";
+
+        let mut pool = StringPool::new();
+        let map_file = parse_map_interned(input, &mut pool).unwrap();
+        let symbols = &map_file.sections[0].symbols;
+
+        assert_eq!(map_file.sections[0].name, SectionName::Text);
+        assert_eq!(pool.len(), 4);
+        assert_eq!(symbols[0].origin.obj, symbols[1].origin.obj);
+        assert_eq!(symbols[0].id, symbols[2].id);
+        assert_ne!(symbols[0].id, symbols[1].id);
+        match symbols[1].id {
+            Identifier::Named { name, .. } => assert_eq!(&pool[name], "bar"),
+            _ => panic!("{:?}", symbols[1].id),
+        }
+        assert_eq!(pool.get("other.c.o"), Some(symbols[2].origin.obj));
+    }
+}
@@ -0,0 +1,188 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    num::ParseIntError,
+};
+
+use crate::{
+    map::{hex_digits, identifier, Identifier},
+    span::{FieldKind, Fields},
+};
+use nom::{
+    bytes::complete::tag,
+    character::complete::space1,
+    combinator::{consumed, map},
+    error::{context, ContextError, FromExternalError, ParseError},
+    sequence::{delimited, preceded, tuple},
+    IResult,
+};
+
+/// A branch trampoline generated by the linker, e.g.
+/// `80003110 branch island __unnamed_island_1 created for OSReport`.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(serialize = "S: serde::Serialize + std::fmt::Display"))
+)]
+pub struct Island<S: Eq + PartialEq> {
+    /// The address of the island's code.
+    pub virt_addr: u32,
+    pub name: Identifier<S>,
+    /// The symbol that the island branches to.
+    pub target: Identifier<S>,
+}
+
+impl<S: Eq + PartialEq> Island<S> {
+    /// The size of the code of a PowerPC branch island in bytes: it loads the
+    /// address of its target into `r12` with `lis` and `addi`, then branches
+    /// there with `mtctr` and `bctr`. Mixed mode islands and the islands of
+    /// ARM maps are veneers of other sizes, which the map does not give.
+    pub const BRANCH_SIZE: u32 = 16;
+
+    pub fn map_str<T, F>(self, f: &mut F) -> Island<T>
+    where
+        T: Eq + PartialEq,
+        F: FnMut(S) -> T,
+    {
+        Island {
+            virt_addr: self.virt_addr,
+            name: self.name.map_str(f),
+            target: self.target.map_str(f),
+        }
+    }
+}
+
+impl<S: Eq + PartialEq + Display> Display for Island<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} created for {}", self.name, self.target)
+    }
+}
+
+pub fn mixed_mode_title<'a, E>(input: &'a str) -> IResult<&'a str, &'a str, E>
+where
+    E: ParseError<&'a str>,
+{
+    tag("Mixed Mode Islands")(input)
+}
+
+pub fn branch_title<'a, E>(input: &'a str) -> IResult<&'a str, &'a str, E>
+where
+    E: ParseError<&'a str>,
+{
+    tag("Branch Islands")(input)
+}
+
+pub(crate) fn mixed_mode_entry<'a, E>(
+    input: &'a str,
+) -> IResult<&'a str, (Island<&'a str>, Fields<'a>), E>
+where
+    E: ParseError<&'a str>
+        + FromExternalError<&'a str, ParseIntError>
+        + FromExternalError<&'a str, &'static str>
+        + ContextError<&'a str>,
+{
+    context("mixed mode island", island("mixed mode island "))(input)
+}
+
+pub(crate) fn branch_entry<'a, E>(
+    input: &'a str,
+) -> IResult<&'a str, (Island<&'a str>, Fields<'a>), E>
+where
+    E: ParseError<&'a str>
+        + FromExternalError<&'a str, ParseIntError>
+        + FromExternalError<&'a str, &'static str>
+        + ContextError<&'a str>,
+{
+    context("branch island", island("branch island "))(input)
+}
+
+/// Parses a row after its address, which starts with `kind`, e.g.
+/// `branch island `.
+fn island<'a, E>(
+    kind: &'static str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, (Island<&'a str>, Fields<'a>), E>
+where
+    E: ParseError<&'a str>
+        + FromExternalError<&'a str, ParseIntError>
+        + FromExternalError<&'a str, &'static str>
+        + ContextError<&'a str>,
+{
+    map(
+        tuple((
+            delimited(
+                space1,
+                context("virtual address", consumed(hex_digits(8))),
+                space1,
+            ),
+            preceded(tag(kind), context("island", consumed(identifier))),
+            preceded(
+                tag(" created for "),
+                context("target", consumed(identifier)),
+            ),
+        )),
+        |(
+            (virt_text, virt_addr),
+            (name_text, name),
+            (target_text, target),
+        )| {
+            let fields = Fields::new()
+                .with(FieldKind::VirtAddr, virt_text)
+                .with(FieldKind::Identifier, name_text)
+                .with(FieldKind::Target, target_text);
+            let island = Island {
+                virt_addr,
+                name,
+                target,
+            };
+            (island, fields)
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        island_table::Island,
+        map::{Identifier, Line, LineParser},
+        utils::test_utils::assert_diff,
+    };
+
+    #[test]
+    fn test_island_table() {
+        let input = "\
+Mixed Mode Islands\r\n\
+\x20 80003100 mixed mode island __ptmf_scall_island created for __ptmf_scall\r\n\
+\r\n\
+Branch Islands\r\n\
+\x20 80003110 branch island @1234 created for OSReport\r\n\
+";
+
+        let named = |name| Identifier::Named {
+            name,
+            instance: None,
+        };
+        let expected = vec![
+            Line::MixedModeIslandsTitle,
+            Line::MixedModeIsland(Island {
+                virt_addr: 0x80003100,
+                name: named("__ptmf_scall_island"),
+                target: named("__ptmf_scall"),
+            }),
+            Line::Empty,
+            Line::BranchIslandsTitle,
+            Line::BranchIsland(Island {
+                virt_addr: 0x80003110,
+                name: Identifier::Relative { idx: 1234 },
+                target: named("OSReport"),
+            }),
+        ];
+
+        let mut parser = LineParser::new();
+        let actual = input
+            .lines()
+            .map(|line| parser.parse(line))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_diff(&expected, &actual);
+    }
+}
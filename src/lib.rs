@@ -1,7 +1,114 @@
+pub mod cache;
+pub mod demangle;
+pub mod document;
+pub mod dol;
+pub mod error;
+pub mod export;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod index;
+pub mod intern;
+pub mod island_table;
 pub mod linker_table;
 pub mod map;
+pub mod map_file;
 pub mod memory_table;
+#[cfg(feature = "ndjson")]
+pub mod ndjson;
+pub mod parsers;
+#[cfg(feature = "python")]
+pub mod python;
 pub mod section_table;
+pub mod span;
 pub mod tree;
 pub mod utils;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 pub mod windows;
+pub mod workspace;
+
+pub use error::Error;
+
+use intern::{Handle, StringPool};
+use map::{Identifier, Line, Origin, SectionName};
+use map_file::MapFile;
+use nom::combinator::all_consuming;
+use nom_supreme::error::ErrorTree;
+
+/// Parses a single line of a map without any context.
+pub fn parse_line(input: &str) -> Result<Line<&str>, Error> {
+    map::any_line::<ErrorTree<&str>>(input)
+        .map(|(_, line)| line)
+        .map_err(|err| Error::from_nom(input, err))
+}
+
+/// Parses a whole map file into its tables.
+pub fn parse_map(input: &str) -> Result<MapFile<&str>, Error> {
+    map_file::map_file::<ErrorTree<&str>>(input)
+        .map_err(|err| Error::from_nom(input, err))
+}
+
+/// Parses a whole map file, converting every string through `f` as each line
+/// is parsed, e.g. to own them.
+pub fn parse_map_with<'a, S, F>(
+    input: &'a str,
+    f: F,
+) -> Result<MapFile<S>, Error>
+where
+    S: Eq + PartialEq,
+    F: FnMut(&'a str) -> S,
+{
+    map_file::map_file_with::<ErrorTree<&str>, _, _>(input, f)
+        .map_err(|err| Error::from_nom(input, err))
+}
+
+/// Parses a whole map file, interning every string into `pool`.
+pub fn parse_map_interned(
+    input: &str,
+    pool: &mut StringPool,
+) -> Result<MapFile<Handle>, Error> {
+    map_file::map_file_interned::<ErrorTree<&str>>(input, pool)
+        .map_err(|err| Error::from_nom(input, err))
+}
+
+/// Parses a whole map file in parallel, one table per task.
+#[cfg(feature = "rayon")]
+pub fn par_parse_map(input: &str) -> Result<MapFile<&str>, Error> {
+    map_file::par_map_file::<ErrorTree<&str>>(input)
+        .map_err(|err| Error::from_nom(input, err))
+}
+
+/// Parses a whole map file, skipping lines that cannot be parsed and
+/// returning an error for each of them alongside the tables.
+pub fn parse_map_lenient(input: &str) -> (MapFile<&str>, Vec<Error>) {
+    let (map_file, errors) =
+        map_file::map_file_lenient::<ErrorTree<&str>>(input);
+    let errors = errors
+        .into_iter()
+        .map(|err| Error::from_nom(input, err))
+        .collect();
+
+    (map_file, errors)
+}
+
+/// Parses a symbol name, e.g. `__start`, `@123` or `...data.0`.
+pub fn parse_identifier(input: &str) -> Result<Identifier<&str>, Error> {
+    all_consuming(map::identifier::<ErrorTree<&str>>)(input)
+        .map(|(_, id)| id)
+        .map_err(|err| Error::from_nom(input, err))
+}
+
+/// Parses a section name, e.g. `.text` or `extabindex`.
+pub fn parse_section_name(input: &str) -> Result<SectionName<&str>, Error> {
+    all_consuming(map::section_name::<ErrorTree<&str>>)(input)
+        .map(|(_, name)| name)
+        .map_err(|err| Error::from_nom(input, err))
+}
+
+/// Parses an object file and optional source file, e.g.
+/// `MSL_C.PPCEABI.bare.H.a printf.o (asm)`.
+pub fn parse_origin(input: &str) -> Result<Origin<&str>, Error> {
+    all_consuming(map::origin::<ErrorTree<&str>>)(input)
+        .map(|(_, origin)| origin)
+        .map_err(|err| Error::from_nom(input, err))
+}
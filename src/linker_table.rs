@@ -1,19 +1,51 @@
-use std::num::ParseIntError;
+use std::{
+    fmt::{self, Display, Formatter},
+    num::ParseIntError,
+};
 
-use crate::map::{c_name, hex, padded, Line};
+use crate::{
+    map::{c_name, column, hex_digits, Line},
+    span::{FieldKind, Fields},
+};
 use nom::{
     bytes::complete::tag,
-    character::complete::char,
-    combinator::map,
-    error::{FromExternalError, ParseError},
+    character::complete::space1,
+    combinator::{consumed, map},
+    error::{context, ContextError, FromExternalError, ParseError},
     sequence::{pair, terminated},
-    IResult, Parser,
+    IResult,
 };
 
-#[derive(Debug, Eq, PartialEq, Hash)]
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Entry<S> {
     pub name: S,
     pub virt_addr: u32,
+    /// The number of spaces before the name, which is right-aligned.
+    pub padding: usize,
+}
+
+impl<S> Entry<S> {
+    pub fn map_str<T, F>(self, f: &mut F) -> Entry<T>
+    where
+        F: FnMut(S) -> T,
+    {
+        Entry {
+            name: f(self.name),
+            virt_addr: self.virt_addr,
+            padding: self.padding,
+        }
+    }
+}
+
+impl<S: Display> Display for Entry<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:3$}{} {:08x}",
+            "", self.name, self.virt_addr, self.padding
+        )
+    }
 }
 
 pub fn title<'a, E: ParseError<&'a str>>(
@@ -22,21 +54,51 @@ pub fn title<'a, E: ParseError<&'a str>>(
     map(tag("Linker generated symbols:"), |_| Line::LinkerTitle)(input)
 }
 
+/// Kept for compatibility, see [`parsers`](crate::parsers).
 pub fn entry<'a, E>(input: &'a str) -> IResult<&'a str, Entry<&'a str>, E>
 where
     E: ParseError<&'a str>
         + FromExternalError<&'a str, ParseIntError>
         + FromExternalError<&'a str, &'static str>,
 {
-    map(
-        pair(terminated(padded(25).and_then(c_name), char(' ')), hex(8)),
-        |(name, virt_addr)| Entry { name, virt_addr },
+    crate::parsers::linker_table::entry(input)
+}
+
+/// Like [`entry`], but also locates its fields.
+pub(crate) fn entry_located<'a, E>(
+    input: &'a str,
+) -> IResult<&'a str, (Entry<&'a str>, Fields<'a>), E>
+where
+    E: ParseError<&'a str>
+        + FromExternalError<&'a str, ParseIntError>
+        + FromExternalError<&'a str, &'static str>
+        + ContextError<&'a str>,
+{
+    context(
+        "linker symbol",
+        map(
+            pair(
+                terminated(context("symbol", column(c_name)), space1),
+                context("virtual address", consumed(hex_digits(8))),
+            ),
+            |((padding, name), (virt_text, virt_addr))| {
+                let fields = Fields::new()
+                    .with(FieldKind::Name, name)
+                    .with(FieldKind::VirtAddr, virt_text);
+                let entry = Entry {
+                    name,
+                    virt_addr,
+                    padding,
+                };
+                (entry, fields)
+            },
+        ),
     )(input)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{entry, title};
+    use super::{entry_located, title};
     use crate::{linker_table::Entry, utils::test_utils::assert_diff};
     use nom::{
         branch::alt,
@@ -61,16 +123,20 @@ Linker generated symbols:\r\n\
             Line::LinkerEntry(Entry {
                 name: "_db_stack_addr",
                 virt_addr: 0x804f0c00,
+                padding: 11,
             }),
             Line::LinkerEntry(Entry {
                 name: "_ctors",
                 virt_addr: 0,
+                padding: 19,
             }),
         ];
 
         let mut parser = alt::<_, _, ErrorTree<&str>, _>((
             all_consuming(map(title, |_| Line::LinkerTitle)),
-            all_consuming(map(entry, Line::LinkerEntry)),
+            all_consuming(map(entry_located, |(entry, _)| {
+                Line::LinkerEntry(entry)
+            })),
         ));
 
         let (input_len, expected_len) = (&input.len(), &expected.len());
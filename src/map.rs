@@ -1,39 +1,70 @@
 use crate::{
-    linker_table, memory_table, section_table, tree, windows::filename,
+    error::Error,
+    island_table, linker_table, memory_table, section_table,
+    span::{Field, FieldKind, Fields},
+    tree,
+    windows::filename,
 };
 use nom::{
     branch::alt,
     bytes::complete::{
         is_a, tag, take, take_while, take_while1, take_while_m_n,
     },
-    character::complete::{alpha1, alphanumeric1, char, digit1, line_ending},
-    combinator::{all_consuming, eof, map, map_res, opt, recognize},
-    error::{FromExternalError, ParseError},
+    character::complete::{
+        alpha1, alphanumeric1, char, digit1, line_ending, satisfy, space0,
+    },
+    combinator::{
+        all_consuming, eof, map, map_opt, map_res, not, opt, recognize,
+    },
+    error::{ContextError, ErrorKind, FromExternalError, ParseError},
     multi::many0_count,
     sequence::{pair, preceded, separated_pair, terminated, tuple},
     AsChar, IResult, Parser,
 };
-use std::num::ParseIntError;
+use nom_supreme::error::ErrorTree;
+use std::{
+    fmt::{self, Display, Formatter},
+    num::ParseIntError,
+    str::FromStr,
+};
 
-#[derive(Debug, Eq, PartialEq, Hash)]
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(serialize = "S: serde::Serialize + std::fmt::Display"))
+)]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "data"))]
 pub enum Line<S: Eq + PartialEq> {
     Empty,
     TreeTitle(S),
     TreeNode(tree::Node<S>),
     SectionTitle(SectionName<S>),
-    SectionColumns0,
+    /// The first line of the column header, which announces the layout of
+    /// the rows that follow.
+    SectionColumns0(section_table::Layout),
     SectionColumns1,
     SectionSeparator,
     SectionSymbol(section_table::Symbol<S>),
     MemoryTitle,
-    MemoryColumns0,
+    /// The first line of the column header, which announces the layout of
+    /// the rows that follow.
+    MemoryColumns0(memory_table::Layout),
     MemoryColumns1,
     MemoryEntry(memory_table::Entry<S>),
     LinkerTitle,
     LinkerEntry(linker_table::Entry<S>),
+    MixedModeIslandsTitle,
+    MixedModeIsland(island_table::Island<S>),
+    BranchIslandsTitle,
+    BranchIsland(island_table::Island<S>),
+    /// A line that could not be parsed, kept as is by
+    /// [`LineParser::parse_lenient`].
+    Unknown(S),
 }
 
-#[derive(Debug, Eq, PartialEq, Hash)]
+/// Serialized as its text in the map, e.g. `__start` or `@1234`.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
 pub enum Identifier<S: Eq + PartialEq> {
     Relative {
         idx: u32,
@@ -55,75 +86,1024 @@ pub enum Identifier<S: Eq + PartialEq> {
     DotL {
         name: S,
     },
+    Mapping(MappingSymbol),
+}
+
+/// ARM ELF mapping symbols, which mark the start of ARM code, Thumb code or
+/// data within a section.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum MappingSymbol {
+    /// `$a`
+    Arm,
+    /// `$t`
+    Thumb,
+    /// `$d`
+    Data,
 }
 
-#[derive(Debug, Eq, PartialEq, Hash)]
+/// The target a map was linked for.
+///
+/// Ordered by how decisive a line is, so that the greatest dialect of the
+/// lines of a map is that of the map: the small data sections of PowerPC
+/// EABI decide on [`Dialect::PowerPc`], unless an ARM line is seen as well.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Clone, Copy, Hash)]
+pub enum Dialect {
+    /// GameCube and Wii, linked with `mwldeppc`.
+    PowerPc,
+    /// Nintendo DS, linked with `mwldarm`.
+    Arm,
+}
+
+/// Serialized as its text in the map, e.g. `.text`.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
 pub enum SectionName<S> {
     Bss,
-    Ctors,
+    /// `.ctors`, optionally with a priority suffix, e.g. `.ctors$10`.
+    Ctors(Option<u16>),
     Data,
-    Dtors,
+    /// `.dtors`, optionally with a priority suffix, e.g. `.dtors$10`.
+    Dtors(Option<u16>),
     ExTab,
     ExTabIndex,
     Init,
+    InitArray,
+    FiniArray,
     RoData,
     SBss,
+    SBss0,
     SBss2,
     SData,
+    SData0,
     SData2,
+    /// `.PPC.EMB.sbss0`
+    EmbSBss0,
+    /// `.PPC.EMB.sdata0`
+    EmbSData0,
+    /// `.PPC.EMB.apuinfo`
+    EmbApuInfo,
     Text,
+    /// ARM instruction TCM.
+    Itcm,
+    /// ARM data TCM.
+    Dtcm,
+    /// An ARM overlay, named by the linker command file without a dot.
+    Overlay(S),
     Unknown(S),
 }
 
+impl<S: Eq + PartialEq> Identifier<S> {
+    /// One of `named`, `mangled`, `relative`, `string_base`, `section`,
+    /// `dot_l` or `mapping`.
+    pub fn kind(&self) -> &'static str {
+        use Identifier::*;
+
+        match self {
+            Relative { .. } => "relative",
+            StringBase { .. } => "string_base",
+            Named { .. } => "named",
+            Mangled { .. } => "mangled",
+            Section { .. } => "section",
+            DotL { .. } => "dot_l",
+            Mapping(_) => "mapping",
+        }
+    }
+
+    pub fn map_str<T, F>(self, f: &mut F) -> Identifier<T>
+    where
+        T: Eq + PartialEq,
+        F: FnMut(S) -> T,
+    {
+        use Identifier::*;
+
+        match self {
+            Relative { idx } => Relative { idx },
+            StringBase { idx } => StringBase { idx },
+            Named { name, instance } => Named {
+                name: f(name),
+                instance,
+            },
+            Mangled { name } => Mangled { name: f(name) },
+            Section { name, idx } => Section {
+                name: name.map_str(f),
+                idx,
+            },
+            DotL { name } => DotL { name: f(name) },
+            Mapping(symbol) => Mapping(symbol),
+        }
+    }
+}
+
+impl<S> DebugSectionName<S> {
+    pub fn map_str<T, F>(self, f: &mut F) -> DebugSectionName<T>
+    where
+        F: FnMut(S) -> T,
+    {
+        use DebugSectionName::*;
+
+        match self {
+            Main => Main,
+            Line => Line,
+            Abbrev => Abbrev,
+            Aranges => Aranges,
+            Frame => Frame,
+            Info => Info,
+            DebugLine => DebugLine,
+            Loc => Loc,
+            MacInfo => MacInfo,
+            PubNames => PubNames,
+            PubTypes => PubTypes,
+            Ranges => Ranges,
+            SfNames => SfNames,
+            SrcInfo => SrcInfo,
+            Str => Str,
+            Comment => Comment,
+            MwCats(name) => MwCats(name.map(|name| name.map_str(f))),
+            Unknown(name) => Unknown(f(name)),
+        }
+    }
+}
+
+impl<S: Eq + PartialEq> Origin<S> {
+    pub fn map_str<T, F>(self, f: &mut F) -> Origin<T>
+    where
+        T: Eq + PartialEq,
+        F: FnMut(S) -> T,
+    {
+        Origin {
+            obj: f(self.obj),
+            src: self.src.map(f),
+            asm: self.asm,
+        }
+    }
+}
+
+impl<S> SectionName<S> {
+    pub fn map_str<T, F>(self, f: &mut F) -> SectionName<T>
+    where
+        F: FnMut(S) -> T,
+    {
+        use SectionName::*;
+
+        match self {
+            Bss => Bss,
+            Ctors(priority) => Ctors(priority),
+            Data => Data,
+            Dtors(priority) => Dtors(priority),
+            ExTab => ExTab,
+            ExTabIndex => ExTabIndex,
+            Init => Init,
+            InitArray => InitArray,
+            FiniArray => FiniArray,
+            RoData => RoData,
+            SBss => SBss,
+            SBss0 => SBss0,
+            SBss2 => SBss2,
+            SData => SData,
+            SData0 => SData0,
+            SData2 => SData2,
+            EmbSBss0 => EmbSBss0,
+            EmbSData0 => EmbSData0,
+            EmbApuInfo => EmbApuInfo,
+            Text => Text,
+            Itcm => Itcm,
+            Dtcm => Dtcm,
+            Overlay(name) => Overlay(f(name)),
+            Unknown(name) => Unknown(f(name)),
+        }
+    }
+
+    /// The dialect this section is specific to, if any. `.sdata` and `.sbss`
+    /// are left out, as ARM linker command files name them as well.
+    pub fn dialect(&self) -> Option<Dialect> {
+        use SectionName::*;
+
+        match self {
+            SBss0 | SBss2 | SData0 | SData2 | EmbSBss0 | EmbSData0
+            | EmbApuInfo => Some(Dialect::PowerPc),
+            Itcm | Dtcm | Overlay(_) => Some(Dialect::Arm),
+            _ => None,
+        }
+    }
+
+    /// Whether the section is addressed relative to one of the small data
+    /// base registers (`r13`, `r2` or `r0`).
+    pub fn is_small_data(&self) -> bool {
+        use SectionName::*;
+
+        matches!(
+            self,
+            SBss | SBss0
+                | SBss2
+                | SData
+                | SData0
+                | SData2
+                | EmbSBss0
+                | EmbSData0
+        )
+    }
+
+    /// Whether the section is zero-initialized, i.e. takes up no space in
+    /// the executable.
+    pub fn is_zero_initialized(&self) -> bool {
+        use SectionName::*;
+
+        matches!(self, Bss | SBss | SBss0 | SBss2 | EmbSBss0)
+    }
+
+    /// Whether the section is read-only once loaded.
+    pub fn is_read_only(&self) -> bool {
+        use SectionName::*;
+
+        matches!(
+            self,
+            Ctors(_)
+                | Dtors(_)
+                | ExTab
+                | ExTabIndex
+                | Init
+                | InitArray
+                | FiniArray
+                | RoData
+                | SBss2
+                | SData2
+                | EmbApuInfo
+                | Text
+        )
+    }
+
+    /// Whether the section contains code.
+    pub fn is_executable(&self) -> bool {
+        matches!(
+            self,
+            SectionName::Init | SectionName::Text | SectionName::Itcm
+        )
+    }
+}
+
+impl<S: Eq + PartialEq> Line<S> {
+    pub fn map_str<T, F>(self, f: &mut F) -> Line<T>
+    where
+        T: Eq + PartialEq,
+        F: FnMut(S) -> T,
+    {
+        use Line::*;
+
+        match self {
+            Empty => Empty,
+            TreeTitle(root) => TreeTitle(f(root)),
+            TreeNode(node) => TreeNode(node.map_str(f)),
+            SectionTitle(name) => SectionTitle(name.map_str(f)),
+            SectionColumns0(layout) => SectionColumns0(layout),
+            SectionColumns1 => SectionColumns1,
+            SectionSeparator => SectionSeparator,
+            SectionSymbol(symbol) => SectionSymbol(symbol.map_str(f)),
+            MemoryTitle => MemoryTitle,
+            MemoryColumns0(layout) => MemoryColumns0(layout),
+            MemoryColumns1 => MemoryColumns1,
+            MemoryEntry(entry) => MemoryEntry(entry.map_str(f)),
+            LinkerTitle => LinkerTitle,
+            LinkerEntry(entry) => LinkerEntry(entry.map_str(f)),
+            MixedModeIslandsTitle => MixedModeIslandsTitle,
+            MixedModeIsland(island) => MixedModeIsland(island.map_str(f)),
+            BranchIslandsTitle => BranchIslandsTitle,
+            BranchIsland(island) => BranchIsland(island.map_str(f)),
+            Unknown(text) => Unknown(f(text)),
+        }
+    }
+
+    /// Whether this line is the title of a table, which starts a new table.
+    pub fn is_title(&self) -> bool {
+        use Line::*;
+
+        matches!(
+            self,
+            TreeTitle(_)
+                | SectionTitle(_)
+                | MemoryTitle
+                | LinkerTitle
+                | MixedModeIslandsTitle
+                | BranchIslandsTitle
+        )
+    }
+
+    /// The dialect this line is specific to, if any.
+    pub fn dialect(&self) -> Option<Dialect> {
+        use Line::*;
+
+        match self {
+            SectionTitle(name) => name.dialect(),
+            SectionSymbol(symbol) => match symbol.id {
+                Identifier::Mapping(_) => Some(Dialect::Arm),
+                _ => None,
+            },
+            MemoryEntry(memory_table::Entry {
+                data: memory_table::Data::Main { name, .. },
+                ..
+            }) => name.dialect(),
+            _ => None,
+        }
+    }
+}
+
+/// Serialized as its text in the map, e.g. `.debug_info`.
 #[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
-pub enum DebugSectionName {
+pub enum DebugSectionName<S> {
     Main,
     Line,
     Abbrev,
     Aranges,
+    Frame,
     Info,
+    DebugLine,
+    Loc,
+    MacInfo,
+    PubNames,
+    PubTypes,
+    Ranges,
     SfNames,
     SrcInfo,
     Str,
+    Comment,
+    /// CodeWarrior's `.mwcats` sections, optionally suffixed with the name of
+    /// the section they describe, e.g. `.mwcats.text`.
+    MwCats(Option<SectionName<S>>),
+    /// The name as it appears in the map, including its leading `.`.
+    Unknown(S),
 }
 
-#[derive(Debug, Eq, PartialEq, Hash)]
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Origin<S: Eq + PartialEq> {
     pub obj: S,
     pub src: Option<S>,
     pub asm: bool,
 }
 
+impl FromStr for Identifier<String> {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        crate::parse_identifier(s).map(|id| id.map_str(&mut str::to_owned))
+    }
+}
+
+impl FromStr for SectionName<String> {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        crate::parse_section_name(s)
+            .map(|name| name.map_str(&mut str::to_owned))
+    }
+}
+
+impl FromStr for Origin<String> {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        crate::parse_origin(s).map(|origin| origin.map_str(&mut str::to_owned))
+    }
+}
+
+impl<S: Eq + PartialEq + Display> Display for Identifier<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        use Identifier::*;
+
+        match self {
+            Relative { idx } => write!(f, "@{idx}"),
+            StringBase { idx } => write!(f, "@stringBase{idx}"),
+            Named {
+                name,
+                instance: Some(instance),
+            } => write!(f, "{name}${instance}"),
+            Named {
+                name,
+                instance: None,
+            }
+            | Mangled { name } => write!(f, "{name}"),
+            Section {
+                name,
+                idx: Some(idx),
+            } => write!(f, "..{name}.{idx}"),
+            Section { name, idx: None } => write!(f, "{name}"),
+            DotL { name } => write!(f, ".L{name}"),
+            Mapping(MappingSymbol::Arm) => f.write_str("$a"),
+            Mapping(MappingSymbol::Thumb) => f.write_str("$t"),
+            Mapping(MappingSymbol::Data) => f.write_str("$d"),
+        }
+    }
+}
+
+impl<S: Eq + PartialEq + Display> Display for Origin<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ", self.obj)?;
+        if let Some(src) = &self.src {
+            write!(f, "{src}")?;
+        }
+        if self.asm {
+            f.write_str(" (asm)")?;
+        }
+        Ok(())
+    }
+}
+
+impl<S: Display> Display for SectionName<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        use SectionName::*;
+
+        match self {
+            Bss => f.write_str(".bss"),
+            Ctors(None) => f.write_str(".ctors"),
+            Ctors(Some(priority)) => write!(f, ".ctors${priority}"),
+            Data => f.write_str(".data"),
+            Dtors(None) => f.write_str(".dtors"),
+            Dtors(Some(priority)) => write!(f, ".dtors${priority}"),
+            ExTab => f.write_str("extab"),
+            ExTabIndex => f.write_str("extabindex"),
+            Init => f.write_str(".init"),
+            InitArray => f.write_str(".init_array"),
+            FiniArray => f.write_str(".fini_array"),
+            RoData => f.write_str(".rodata"),
+            SBss => f.write_str(".sbss"),
+            SBss0 => f.write_str(".sbss0"),
+            SBss2 => f.write_str(".sbss2"),
+            SData => f.write_str(".sdata"),
+            SData0 => f.write_str(".sdata0"),
+            SData2 => f.write_str(".sdata2"),
+            EmbSBss0 => f.write_str(".PPC.EMB.sbss0"),
+            EmbSData0 => f.write_str(".PPC.EMB.sdata0"),
+            EmbApuInfo => f.write_str(".PPC.EMB.apuinfo"),
+            Text => f.write_str(".text"),
+            Itcm => f.write_str(".itcm"),
+            Dtcm => f.write_str(".dtcm"),
+            Overlay(name) => write!(f, "{name}"),
+            Unknown(name) => write!(f, ".{name}"),
+        }
+    }
+}
+
+impl<S: Display> Display for DebugSectionName<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        use DebugSectionName::*;
+
+        match self {
+            Main => f.write_str(".debug"),
+            Line => f.write_str(".line"),
+            Abbrev => f.write_str(".debug_abbrev"),
+            Aranges => f.write_str(".debug_aranges"),
+            Frame => f.write_str(".debug_frame"),
+            Info => f.write_str(".debug_info"),
+            DebugLine => f.write_str(".debug_line"),
+            Loc => f.write_str(".debug_loc"),
+            MacInfo => f.write_str(".debug_macinfo"),
+            PubNames => f.write_str(".debug_pubnames"),
+            PubTypes => f.write_str(".debug_pubtypes"),
+            Ranges => f.write_str(".debug_ranges"),
+            SfNames => f.write_str(".debug_sfnames"),
+            SrcInfo => f.write_str(".debug_srcinfo"),
+            Str => f.write_str(".debug_str"),
+            Comment => f.write_str(".comment"),
+            MwCats(None) => f.write_str(".mwcats"),
+            MwCats(Some(name)) => write!(f, ".mwcats{name}"),
+            Unknown(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<S: Eq + PartialEq + Display> serde::Serialize for Identifier<S> {
+    fn serialize<T: serde::Serializer>(
+        &self,
+        serializer: T,
+    ) -> Result<T::Ok, T::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<S: Display> serde::Serialize for SectionName<S> {
+    fn serialize<T: serde::Serializer>(
+        &self,
+        serializer: T,
+    ) -> Result<T::Ok, T::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<S: Display> serde::Serialize for DebugSectionName<S> {
+    fn serialize<T: serde::Serializer>(
+        &self,
+        serializer: T,
+    ) -> Result<T::Ok, T::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// The table that the rows following a title belong to.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Copy, Hash)]
+pub enum Context {
+    #[default]
+    None,
+    Tree,
+    /// A section layout, along with the layout of its rows.
+    Section(section_table::Layout),
+    /// A memory map, along with the layout of its rows.
+    Memory(memory_table::Layout),
+    Linker,
+    MixedModeIslands,
+    BranchIslands,
+}
+
+impl Context {
+    /// The context that follows `line`.
+    pub fn next<S: Eq + PartialEq>(self, line: &Line<S>) -> Self {
+        match line {
+            Line::TreeTitle(_) => Context::Tree,
+            Line::SectionTitle(_) => Context::Section(Default::default()),
+            Line::SectionColumns0(layout) => Context::Section(*layout),
+            Line::MemoryTitle => Context::Memory(Default::default()),
+            Line::MemoryColumns0(layout) => Context::Memory(*layout),
+            Line::LinkerTitle => Context::Linker,
+            Line::MixedModeIslandsTitle => Context::MixedModeIslands,
+            Line::BranchIslandsTitle => Context::BranchIslands,
+            _ => self,
+        }
+    }
+
+    /// Whether `line` can appear in this table, i.e. it is not a row of
+    /// another table.
+    pub fn accepts<S: Eq + PartialEq>(self, line: &Line<S>) -> bool {
+        use Line::*;
+
+        match line {
+            TreeNode(_) => self == Context::Tree,
+            SectionColumns0(_) | SectionColumns1 | SectionSeparator
+            | SectionSymbol(_) => matches!(self, Context::Section(_)),
+            MemoryColumns0(_) | MemoryColumns1 | MemoryEntry(_) => {
+                matches!(self, Context::Memory(_))
+            }
+            LinkerEntry(_) => self == Context::Linker,
+            MixedModeIsland(_) => self == Context::MixedModeIslands,
+            BranchIsland(_) => self == Context::BranchIslands,
+            _ => true,
+        }
+    }
+}
+
+/// Parses lines one after another, only attempting the row parsers of the
+/// table it is currently in.
+///
+/// Unlike [`parse_line`](crate::parse_line), a row that does not belong to
+/// the current table is an error, even if it would be valid in another
+/// table.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Copy, Hash)]
+pub struct LineParser {
+    context: Context,
+}
+
+impl LineParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A parser that resumes within the given table, e.g. after a line that
+    /// was parsed earlier.
+    pub fn with_context(context: Context) -> Self {
+        Self { context }
+    }
+
+    pub fn context(&self) -> Context {
+        self.context
+    }
+
+    /// Parses the next line, which must not contain a line ending.
+    pub fn parse<'a>(
+        &mut self,
+        input: &'a str,
+    ) -> Result<Line<&'a str>, Error> {
+        self.line::<ErrorTree<&str>>(input)
+            .map(|(_, line)| line)
+            .map_err(|err| Error::from_nom(input, err))
+    }
+
+    /// Like [`parse`](Self::parse), but turns a line that does not parse into
+    /// [`Line::Unknown`] instead of failing, returning the error alongside it.
+    /// The context is left unchanged, so the rows of the current table can
+    /// still be parsed after it.
+    pub fn parse_lenient<'a>(
+        &mut self,
+        input: &'a str,
+    ) -> (Line<&'a str>, Option<Error>) {
+        let (line, err) = self.line_lenient::<ErrorTree<&str>>(input);
+        (line, err.map(|err| Error::from_nom(input, err)))
+    }
+
+    /// Like [`parse`](Self::parse), but also returns the location of each
+    /// field within `input`.
+    pub fn parse_located<'a>(
+        &mut self,
+        input: &'a str,
+    ) -> Result<(Line<&'a str>, Vec<Field>), Error> {
+        self.line_located::<ErrorTree<&str>>(input)
+            .map(|(_, (line, fields))| (line, fields.locate(input)))
+            .map_err(|err| Error::from_nom(input, err))
+    }
+
+    pub(crate) fn line<'a, E>(
+        &mut self,
+        input: &'a str,
+    ) -> IResult<&'a str, Line<&'a str>, E>
+    where
+        E: ParseError<&'a str>
+            + FromExternalError<&'a str, ParseIntError>
+            + FromExternalError<&'a str, &'static str>
+            + ContextError<&'a str>,
+    {
+        map(|input| self.line_located(input), |(line, _)| line)(input)
+    }
+
+    /// Parses the next line along with its fields, as slices of `input`.
+    pub(crate) fn line_located<'a, E>(
+        &mut self,
+        input: &'a str,
+    ) -> IResult<&'a str, Located<'a>, E>
+    where
+        E: ParseError<&'a str>
+            + FromExternalError<&'a str, ParseIntError>
+            + FromExternalError<&'a str, &'static str>
+            + ContextError<&'a str>,
+    {
+        let context = self.context;
+        let row = |input| row(context, input);
+        // Titles start in the first column and rows are indented, except for
+        // memory map names that overflow their column.
+        let (input, (line, fields)) = match input.chars().next() {
+            None => fieldless(map(eof, |_| Line::Empty))(input),
+            Some(' ') => row(input),
+            Some(_) => alt((title, row))(input),
+        }?;
+        self.context = self.context.next(&line);
+
+        Ok((input, (line, fields)))
+    }
+
+    pub(crate) fn line_lenient<'a, E>(
+        &mut self,
+        input: &'a str,
+    ) -> (Line<&'a str>, Option<nom::Err<E>>)
+    where
+        E: ParseError<&'a str>
+            + FromExternalError<&'a str, ParseIntError>
+            + FromExternalError<&'a str, &'static str>
+            + ContextError<&'a str>,
+    {
+        match self.line(input) {
+            Ok((_, line)) => (line, None),
+            Err(err) => (Line::Unknown(input), Some(err)),
+        }
+    }
+}
+
+/// Writes lines back out the way the linker does, so that the lines parsed
+/// by [`LineParser`] are reproduced exactly.
+///
+/// The column headers are written for the layout of the current table,
+/// which is tracked the same way as while parsing. Section names that the
+/// linker spells in several ways are written in their usual spelling, e.g.
+/// `_extab` as `extab`.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Copy, Hash)]
+pub struct LineWriter {
+    context: Context,
+}
+
+impl LineWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Writes the next line, without a line ending.
+    pub fn write<S, W>(&mut self, out: &mut W, line: &Line<S>) -> fmt::Result
+    where
+        S: Eq + PartialEq + Display,
+        W: fmt::Write,
+    {
+        use Line::*;
+
+        self.context = self.context.next(line);
+        match line {
+            Empty => Ok(()),
+            TreeTitle(root) => write!(out, "Link map of {root}"),
+            TreeNode(node) => write!(out, "{node}"),
+            SectionTitle(name) => write!(out, "{name} section layout"),
+            SectionColumns0(section_table::Layout::Standard) => {
+                out.write_str("  Starting        Virtual")
+            }
+            SectionColumns0(section_table::Layout::FileOffset) => {
+                out.write_str("  Starting        Virtual  File")
+            }
+            SectionColumns1 => {
+                out.write_str("  address  Size   address")?;
+                match self.context {
+                    Context::Section(section_table::Layout::FileOffset) => {
+                        out.write_str("  offset")
+                    }
+                    _ => Ok(()),
+                }
+            }
+            SectionSeparator => {
+                let len = match self.context {
+                    Context::Section(section_table::Layout::FileOffset) => 33,
+                    _ => 23,
+                };
+                write!(out, "  {:-<len$}", "")
+            }
+            SectionSymbol(symbol) => write!(out, "{symbol}"),
+            MemoryTitle => out.write_str("Memory map:"),
+            MemoryColumns0(layout) => {
+                out.write_str("                   Starting Size     File")?;
+                match layout {
+                    memory_table::Layout::Standard => Ok(()),
+                    memory_table::Layout::Rom => out.write_str(
+                        "     ROM      RAM Buffer  S-Record Bin File Bin File",
+                    ),
+                }
+            }
+            MemoryColumns1 => {
+                out.write_str("                   address           Offset")?;
+                match self.context {
+                    Context::Memory(memory_table::Layout::Rom) => out
+                        .write_str(
+                            "   Address  Address     Line     Offset   Name",
+                        ),
+                    _ => Ok(()),
+                }
+            }
+            MemoryEntry(entry) => write!(out, "{entry}"),
+            LinkerTitle => out.write_str("Linker generated symbols:"),
+            LinkerEntry(entry) => write!(out, "{entry}"),
+            MixedModeIslandsTitle => out.write_str("Mixed Mode Islands"),
+            MixedModeIsland(island) => {
+                write!(
+                    out,
+                    "  {:08x} mixed mode island {island}",
+                    island.virt_addr
+                )
+            }
+            BranchIslandsTitle => out.write_str("Branch Islands"),
+            BranchIsland(island) => {
+                write!(
+                    out,
+                    "  {:08x} branch island {island}",
+                    island.virt_addr
+                )
+            }
+            Unknown(text) => write!(out, "{text}"),
+        }
+    }
+}
+
+/// A line along with the location of its fields.
+type Located<'a> = (Line<&'a str>, Fields<'a>);
+
+/// Turns a row parsed along with its fields into a line.
+fn located<'a, T, E, F, G>(
+    parser: F,
+    mut f: G,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Located<'a>, E>
+where
+    E: ParseError<&'a str>,
+    F: Parser<&'a str, (T, Fields<'a>), E>,
+    G: FnMut(T) -> Line<&'a str>,
+{
+    map(parser, move |(row, fields)| (f(row), fields))
+}
+
+/// Parses a line that has no fields, e.g. a column header.
+fn fieldless<'a, E, F>(
+    parser: F,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Located<'a>, E>
+where
+    E: ParseError<&'a str>,
+    F: Parser<&'a str, Line<&'a str>, E>,
+{
+    map(parser, |line| (line, Fields::new()))
+}
+
+/// Parses a row of the table given by `context`.
+fn row<'a, E>(
+    context: Context,
+    input: &'a str,
+) -> IResult<&'a str, Located<'a>, E>
+where
+    E: ParseError<&'a str>
+        + FromExternalError<&'a str, ParseIntError>
+        + FromExternalError<&'a str, &'static str>
+        + ContextError<&'a str>,
+{
+    use Line::*;
+
+    match context {
+        Context::None => Err(nom::Err::Error(E::from_external_error(
+            input,
+            ErrorKind::Verify,
+            "Table row is outside of its table",
+        ))),
+        Context::Tree => {
+            all_consuming(located(tree::node_located, TreeNode))(input)
+        }
+        Context::Section(layout) => alt((
+            all_consuming(fieldless(section_table::columns0)),
+            |input| section_row(layout, input),
+        ))(input),
+        Context::Memory(layout) => {
+            alt((all_consuming(fieldless(memory_table::columns0)), |input| {
+                memory_row(layout, input)
+            }))(input)
+        }
+        Context::Linker => all_consuming(located(
+            linker_table::entry_located,
+            LinkerEntry,
+        ))(input),
+        Context::MixedModeIslands => all_consuming(located(
+            island_table::mixed_mode_entry,
+            MixedModeIsland,
+        ))(input),
+        Context::BranchIslands => all_consuming(located(
+            island_table::branch_entry,
+            BranchIsland,
+        ))(input),
+    }
+}
+
+fn title<'a, E>(input: &'a str) -> IResult<&'a str, Located<'a>, E>
+where
+    E: ParseError<&'a str>,
+{
+    use Line::*;
+
+    let section = |input| {
+        all_consuming(located(section_table::title_located, SectionTitle))(
+            input,
+        )
+    };
+    let tree = map(tree::title, |root| {
+        (TreeTitle(root), Fields::new().with(FieldKind::Root, root))
+    });
+
+    match input.chars().next() {
+        Some('L') => alt((
+            all_consuming(tree),
+            all_consuming(fieldless(linker_table::title)),
+            section,
+        ))(input),
+        Some('M') => alt((
+            all_consuming(fieldless(map(memory_table::title, |_| {
+                MemoryTitle
+            }))),
+            all_consuming(fieldless(map(
+                island_table::mixed_mode_title,
+                |_| MixedModeIslandsTitle,
+            ))),
+            section,
+        ))(input),
+        Some('B') => alt((
+            all_consuming(fieldless(map(island_table::branch_title, |_| {
+                BranchIslandsTitle
+            }))),
+            section,
+        ))(input),
+        _ => section(input),
+    }
+}
+
+/// Parses a single line without any context, trying the parsers of every
+/// kind of row in turn.
+///
+/// Kept for compatibility, see [`parsers`](crate::parsers).
 pub fn line<'a, E>(input: &'a str) -> IResult<&'a str, Line<&'a str>, E>
 where
     E: ParseError<&'a str>
         + FromExternalError<&'a str, ParseIntError>
         + FromExternalError<&'a str, &'static str>,
+{
+    crate::parsers::line(input)
+}
+
+/// Like [`line`], but attaches the names of the rows and fields it tried to
+/// any error. Section layout and memory map rows are tried with
+/// the standard layout first.
+pub(crate) fn any_line<'a, E>(
+    input: &'a str,
+) -> IResult<&'a str, Line<&'a str>, E>
+where
+    E: ParseError<&'a str>
+        + FromExternalError<&'a str, ParseIntError>
+        + FromExternalError<&'a str, &'static str>
+        + ContextError<&'a str>,
 {
     use Line::*;
 
+    let section_title = |input| {
+        map(all_consuming(section_table::title_located), |(name, _)| {
+            SectionTitle(name)
+        })(input)
+    };
+    let row = |parser: fn(&'a str) -> IResult<&'a str, Located<'a>, E>| {
+        map(parser, |(line, _)| line)
+    };
+
     terminated(
         alt((
             map(eof, |_| Empty),
             all_consuming(map(tree::title, TreeTitle)),
-            all_consuming(map(tree::node, TreeNode)),
-            all_consuming(map(section_table::title, SectionTitle)),
-            all_consuming(map(section_table::columns0, |_| SectionColumns0)),
-            all_consuming(map(section_table::columns1, |_| SectionColumns1)),
-            all_consuming(map(section_table::separator, |_| SectionSeparator)),
-            all_consuming(map(section_table::symbol, SectionSymbol)),
+            all_consuming(map(tree::node_located, |(node, _)| TreeNode(node))),
+            section_title,
+            all_consuming(section_table::columns0),
+            row(|input| section_row(section_table::Layout::Standard, input)),
+            row(|input| section_row(section_table::Layout::FileOffset, input)),
             all_consuming(map(memory_table::title, |_| MemoryTitle)),
-            all_consuming(map(memory_table::columns0, |_| MemoryColumns0)),
-            all_consuming(map(memory_table::columns1, |_| MemoryColumns1)),
-            all_consuming(map(memory_table::entry, MemoryEntry)),
-            all_consuming(map(memory_table::debug_entry, MemoryEntry)),
+            all_consuming(memory_table::columns0),
+            row(|input| memory_row(memory_table::Layout::Standard, input)),
+            row(|input| memory_row(memory_table::Layout::Rom, input)),
             all_consuming(map(linker_table::title, |_| LinkerTitle)),
-            all_consuming(map(linker_table::entry, LinkerEntry)),
+            all_consuming(map(linker_table::entry_located, |(entry, _)| {
+                LinkerEntry(entry)
+            })),
+            all_consuming(map(island_table::mixed_mode_title, |_| {
+                MixedModeIslandsTitle
+            })),
+            all_consuming(map(
+                island_table::mixed_mode_entry,
+                |(island, _)| MixedModeIsland(island),
+            )),
+            all_consuming(map(island_table::branch_title, |_| {
+                BranchIslandsTitle
+            })),
+            all_consuming(map(island_table::branch_entry, |(island, _)| {
+                BranchIsland(island)
+            })),
         )),
         opt(line_ending),
     )(input)
 }
 
+fn section_row<'a, E>(
+    layout: section_table::Layout,
+    input: &'a str,
+) -> IResult<&'a str, Located<'a>, E>
+where
+    E: ParseError<&'a str>
+        + FromExternalError<&'a str, ParseIntError>
+        + FromExternalError<&'a str, &'static str>
+        + ContextError<&'a str>,
+{
+    alt((
+        all_consuming(fieldless(section_table::columns1_for(layout))),
+        all_consuming(fieldless(section_table::separator_for(layout))),
+        all_consuming(located(
+            section_table::symbol_for(layout),
+            Line::SectionSymbol,
+        )),
+    ))(input)
+}
+
+fn memory_row<'a, E>(
+    layout: memory_table::Layout,
+    input: &'a str,
+) -> IResult<&'a str, Located<'a>, E>
+where
+    E: ParseError<&'a str>
+        + FromExternalError<&'a str, ParseIntError>
+        + FromExternalError<&'a str, &'static str>
+        + ContextError<&'a str>,
+{
+    alt((
+        all_consuming(fieldless(memory_table::columns1_for(layout))),
+        all_consuming(located(
+            memory_table::entry_for(layout),
+            Line::MemoryEntry,
+        )),
+    ))(input)
+}
+
+/// Parses a right-aligned column, e.g. a name in the memory map, as its
+/// leading spaces and the whitespace-delimited token after them, which is
+/// parsed with `parser`. Returns the number of leading spaces along with the
+/// output, so that a name that overflows its column still parses.
+pub(crate) fn column<'a, O, E, F>(
+    parser: F,
+) -> impl FnMut(&'a str) -> IResult<&'a str, (usize, O), E>
+where
+    E: ParseError<&'a str>,
+    F: Parser<&'a str, O, E>,
+{
+    pair(
+        map(space0, str::len),
+        take_while1(|c: char| !c.is_whitespace()).and_then(parser),
+    )
+}
+
 // TODO: Custom error type
 pub fn padded<'a, E>(
     len: usize,
@@ -145,17 +1125,79 @@ where
     }
 }
 
+/// Parses exactly `count` hex digits.
+///
+/// Kept for compatibility, see [`parsers`](crate::parsers).
 pub fn hex<'a, E>(
     count: usize,
 ) -> impl FnMut(&'a str) -> IResult<&'a str, u32, E>
 where
     E: ParseError<&'a str> + FromExternalError<&'a str, ParseIntError>,
 {
+    crate::parsers::hex(count)
+}
+
+/// Like [`hex`], but reports how many digits were expected.
+pub(crate) fn hex_digits<'a, E>(
+    count: usize,
+) -> impl FnMut(&'a str) -> IResult<&'a str, u32, E>
+where
+    E: ParseError<&'a str>
+        + FromExternalError<&'a str, ParseIntError>
+        + FromExternalError<&'a str, &'static str>,
+{
+    let expected = match count {
+        2 => "expected 2 hex digits",
+        6 => "expected 6 hex digits",
+        8 => "expected 8 hex digits",
+        _ => "expected hex digits",
+    };
     let mut parser = map_res(
         take_while_m_n(count, count, |c: char| c.is_hex_digit()),
         |x| u32::from_str_radix(x, 16),
     );
-    move |input: &'a str| parser(input)
+    move |input: &'a str| {
+        parser(input).map_err(|err| {
+            err.map(|_: E| {
+                E::from_external_error(
+                    input,
+                    nom::error::ErrorKind::HexDigit,
+                    expected,
+                )
+            })
+        })
+    }
+}
+
+/// Like [`hex`], but accepts anywhere from `min` to 8 digits, e.g. for sizes
+/// that outgrow their column.
+pub(crate) fn hex_min<'a, E>(
+    min: usize,
+) -> impl FnMut(&'a str) -> IResult<&'a str, u32, E>
+where
+    E: ParseError<&'a str>
+        + FromExternalError<&'a str, ParseIntError>
+        + FromExternalError<&'a str, &'static str>,
+{
+    let expected = match min {
+        6 => "expected 6 to 8 hex digits",
+        _ => "expected up to 8 hex digits",
+    };
+    let mut parser =
+        map_res(take_while_m_n(min, 8, |c: char| c.is_hex_digit()), |x| {
+            u32::from_str_radix(x, 16)
+        });
+    move |input: &'a str| {
+        parser(input).map_err(|err| {
+            err.map(|_: E| {
+                E::from_external_error(
+                    input,
+                    nom::error::ErrorKind::HexDigit,
+                    expected,
+                )
+            })
+        })
+    }
 }
 
 pub fn c_name<'a, E>(input: &'a str) -> IResult<&'a str, &'a str, E>
@@ -234,6 +1276,7 @@ where
         })),
         all_consuming(map(cpp_name, |name| Mangled { name })),
         all_consuming(string_base),
+        all_consuming(map(mapping_symbol, Mapping)),
     )))
     .parse(input)
 }
@@ -251,19 +1294,36 @@ where
         extab,
         preceded(
             char('.'),
-            alt((
-                map(tag("bss"), |_| Bss),
-                map(tag("ctors"), |_| Ctors),
-                map(tag("data"), |_| Data),
-                map(tag("dtors"), |_| Dtors),
-                map(tag("init"), |_| Init),
-                map(tag("rodata"), |_| RoData),
-                map(tag("sbss2"), |_| SBss2),
-                map(tag("sbss"), |_| SBss),
-                map(tag("sdata2"), |_| SData2),
-                map(tag("sdata"), |_| SData),
-                map(tag("text"), |_| Text),
-            )),
+            terminated(
+                alt((
+                    map(tag("bss"), |_| Bss),
+                    map(preceded(tag("ctors"), opt(priority)), Ctors),
+                    map(tag("data"), |_| Data),
+                    map(preceded(tag("dtors"), opt(priority)), Dtors),
+                    map(tag("init_array"), |_| InitArray),
+                    map(tag("fini_array"), |_| FiniArray),
+                    map(tag("init"), |_| Init),
+                    map(tag("rodata"), |_| RoData),
+                    map(tag("sbss0"), |_| SBss0),
+                    map(tag("sbss2"), |_| SBss2),
+                    map(tag("sbss"), |_| SBss),
+                    map(tag("sdata0"), |_| SData0),
+                    map(tag("sdata2"), |_| SData2),
+                    map(tag("sdata"), |_| SData),
+                    map(tag("text"), |_| Text),
+                    map(tag("itcm"), |_| Itcm),
+                    map(tag("dtcm"), |_| Dtcm),
+                    preceded(
+                        tag("PPC.EMB."),
+                        alt((
+                            map(tag("sbss0"), |_| EmbSBss0),
+                            map(tag("sdata0"), |_| EmbSData0),
+                            map(tag("apuinfo"), |_| EmbApuInfo),
+                        )),
+                    ),
+                )),
+                not(satisfy(|c| c.is_alphanumeric() || c == '_')),
+            ),
         ),
         map(
             preceded(
@@ -278,6 +1338,13 @@ where
     ))(input)
 }
 
+fn priority<'a, E>(input: &'a str) -> IResult<&'a str, u16, E>
+where
+    E: ParseError<&'a str>,
+{
+    preceded(char('$'), map_opt(digit1, |s: &str| s.parse().ok()))(input)
+}
+
 pub fn origin<'a, E>(input: &'a str) -> IResult<&'a str, Origin<&'a str>, E>
 where
     E: ParseError<&'a str>,
@@ -325,6 +1392,21 @@ where
     )(input)
 }
 
+fn mapping_symbol<'a, E>(input: &'a str) -> IResult<&'a str, MappingSymbol, E>
+where
+    E: ParseError<&'a str>,
+{
+    preceded(
+        char('$'),
+        alt((
+            map(char('a'), |_| MappingSymbol::Arm),
+            map(char('t'), |_| MappingSymbol::Thumb),
+            map(char('d'), |_| MappingSymbol::Data),
+        )),
+    )(input)
+}
+
+#[allow(clippy::from_str_radix_10)]
 fn string_base<'a, E>(
     input: &'a str,
 ) -> IResult<&'a str, Identifier<&'a str>, E>
@@ -341,9 +1423,10 @@ where
 
 #[cfg(test)]
 mod tests {
-    use super::Line;
+    use super::{section_name, Line, LineParser, LineWriter, SectionName};
     use anyhow::{anyhow, Context, Result};
     use memmap2::Mmap;
+    use nom::combinator::all_consuming;
     use nom_supreme::error::ErrorTree;
     use rayon::{prelude::ParallelIterator, str::ParallelString};
     use std::{fs::File, path::PathBuf, str};
@@ -353,7 +1436,8 @@ mod tests {
         let vec = input
             .par_lines()
             .map(|line| {
-                super::line::<ErrorTree<&'a str>>(line).map(|(_, line)| line)
+                super::any_line::<ErrorTree<&'a str>>(line)
+                    .map(|(_, line)| line)
             })
             .collect::<Result<Vec<Line<&'a str>>, _>>()
             .map_err(|err| anyhow!(format!("{err:#?}")))?;
@@ -361,6 +1445,157 @@ mod tests {
         Ok(vec)
     }
 
+    #[test_case(".sdata0", SectionName::SData0 ; "sdata0")]
+    #[test_case(".sbss0", SectionName::SBss0 ; "sbss0")]
+    #[test_case(".sdata", SectionName::SData ; "sdata")]
+    #[test_case(".PPC.EMB.sdata0", SectionName::EmbSData0 ; "emb sdata0")]
+    #[test_case(".PPC.EMB.apuinfo", SectionName::EmbApuInfo ; "emb apuinfo")]
+    #[test_case(".ctors$10", SectionName::Ctors(Some(10)) ; "ctors priority")]
+    #[test_case(".init_array", SectionName::InitArray ; "init array")]
+    #[test_case(".initfoo", SectionName::Unknown("initfoo") ; "unknown")]
+    fn test_section_name(input: &str, expected: SectionName<&str>) {
+        match all_consuming(section_name::<ErrorTree<&str>>)(input) {
+            Ok((_, actual)) => assert_eq!(expected, actual),
+            Err(err) => panic!("{err:#?}"),
+        }
+    }
+
+    #[test]
+    fn test_from_str() {
+        use super::{Identifier, Origin};
+
+        assert_eq!(
+            "finfo$221".parse(),
+            Ok(Identifier::Named {
+                name: "finfo".to_owned(),
+                instance: Some(221),
+            })
+        );
+        assert_eq!(".sdata2".parse(), Ok(SectionName::<String>::SData2));
+        assert_eq!(
+            "MSL_C.PPCEABI.bare.H.a printf.o (asm)".parse(),
+            Ok(Origin {
+                obj: "MSL_C.PPCEABI.bare.H.a".to_owned(),
+                src: Some("printf.o".to_owned()),
+                asm: true,
+            })
+        );
+
+        let err = "foo bar".parse::<Identifier<String>>().unwrap_err();
+        assert_eq!((err.line(), err.column()), (1, 4));
+    }
+
+    #[test]
+    fn test_line_writer() {
+        let input = "\
+Link map of __start\r\n\
+\x20 1] __start (func,global) found in __start.c.o \r\n\
+\x20  2] __init_registers (func,local) found in __start.c.o \r\n\
+\x20   3] _stack_addr found as linker generated symbol\r\n\
+\x20    4] ...data.0 (notype,local) found in OSCache.c.o \r\n\
+\x20     5] vprintf (notype,global) found in MSL_C.PPCEABI.bare.H.a printf.o (asm)\r\n\
+\x20     5] >>> UNREFERENCED DUPLICATE __dt__15CMemoryInStreamFv\r\n\
+\x20     5] >>> (func,weak) found in Kyoto_CW1.a CMemoryInStream.cpp\r\n\
+\r\n\
+.init section layout\r\n\
+\x20 Starting        Virtual\r\n\
+\x20 address  Size   address\r\n\
+\x20 -----------------------\r\n\
+\x20 00000000 0001cc 80003100  1 .init\x20\t__start.o \r\n\
+\x20 00000000 0000f0 80003100 32 __start\x20\t__start.o \r\n\
+\x20 00000250 000000 80003350 __fill_mem (entry of memset) \t__mem.o \r\n\
+\x20 00000000 1000000 80400000 32 lbl_80400000\x20\tmain.c.o \r\n\
+main_overlay_1 section layout\r\n\
+\x20 Starting        Virtual  File\r\n\
+\x20 address  Size   address  offset\r\n\
+\x20 ---------------------------------\r\n\
+\x20 00000000 000088 021c0000 00088c00  4 $a\x20\tovl1.o \r\n\
+\x20 00000010 000000 021c0010 00088c10 $t (entry of $a) \tovl1.o \r\n\
+\r\n\
+Memory map:\r\n\
+\x20                  Starting Size     File\r\n\
+\x20                  address           Offset\r\n\
+\x20           .init  80003100 000023a8 000001c0\r\n\
+\x20      extabindex  80005b80 00000a1c 00002c40\r\n\
+\x20 .a_very_long_section_name  80003100 000000f0 000001c0\r\n\
+\x20  .debug_srcinfo           000000 00000000\r\n\
+\x20    .mwcats.text           000040 000002c0\r\n\
+\r\n\
+Memory map:\r\n\
+\x20                  Starting Size     File     ROM      RAM Buffer  S-Record Bin File Bin File\r\n\
+\x20                  address           Offset   Address  Address     Line     Offset   Name\r\n\
+\x20           .init  ffe00000 00000318 00000200 fff00000 ffe00000           2 00000000 rom.bin\r\n\
+\x20           .text  00003100 000023a8 00000518 fff00318 00003100          14 00000318 rom.bin\r\n\
+\r\n\
+Linker generated symbols:\r\n\
+\x20          _db_stack_addr 804f0c00\r\n\
+\x20 _a_very_long_linker_generated_symbol 804f0c00\r\n\
+\r\n\
+Mixed Mode Islands\r\n\
+\x20 80003100 mixed mode island __ptmf_scall_island created for __ptmf_scall\r\n\
+\r\n\
+Branch Islands\r\n\
+\x20 80003110 branch island @1234 created for OSReport\r\n\
+";
+
+        let mut parser = LineParser::new();
+        let mut writer = LineWriter::new();
+        for input in input.split_terminator("\r\n") {
+            let line = parser.parse(input).unwrap();
+            let mut output = String::new();
+            writer.write(&mut output, &line).unwrap();
+            assert_eq!(output, input);
+        }
+    }
+
+    #[test]
+    fn test_line_parser() {
+        let mut parser = LineParser::new();
+        let mut parse = |input| parser.parse(input).ok();
+
+        assert_eq!(
+            parse("  00000000 000010 80003100  4 foo \tmain.c.o "),
+            None
+        );
+        assert_eq!(
+            parse(".text section layout"),
+            Some(Line::SectionTitle(SectionName::Text))
+        );
+        assert_eq!(
+            parse("  Starting        Virtual"),
+            Some(Line::SectionColumns0(
+                crate::section_table::Layout::Standard
+            ))
+        );
+        assert_eq!(
+            parse("  -----------------------"),
+            Some(Line::SectionSeparator)
+        );
+        assert!(matches!(
+            parse("  00000000 000010 80003100  4 foo \tmain.c.o "),
+            Some(Line::SectionSymbol(_))
+        ));
+        // Rows are not told apart by their first letter.
+        assert!(matches!(
+            parse("  a0000000 000010 a0003100  4 Sfoo \tmain.c.o "),
+            Some(Line::SectionSymbol(_))
+        ));
+        assert_eq!(
+            parse("  1] __start (func,global) found in __start.c.o "),
+            None
+        );
+        assert_eq!(parse(""), Some(Line::Empty));
+        assert_eq!(parse("Memory map:"), Some(Line::MemoryTitle));
+        assert_eq!(
+            parser.context(),
+            super::Context::Memory(crate::memory_table::Layout::Standard)
+        );
+        assert!(matches!(
+            parser.parse("            .init  80003100 000023a8 000001c0"),
+            Ok(Line::MemoryEntry(_))
+        ));
+    }
+
     #[test_case("GALE01.2.map" ; "melee")]
     #[test_case("GM8E01.0.map" ; "prime 1.0")]
     #[test_case("GM8E01.0D.map" ; "prime 1.0 debug")]
@@ -0,0 +1,646 @@
+#[cfg(feature = "rayon")]
+use crate::index::MapIndex;
+use crate::{
+    intern::{Handle, StringPool},
+    island_table::Island,
+    linker_table,
+    map::{Context, Dialect, Line, LineParser, SectionName},
+    memory_table, section_table, tree,
+};
+use nom::error::{ContextError, ErrorKind, FromExternalError, ParseError};
+use std::num::ParseIntError;
+
+/// A closure tree, e.g. `Link map of __start`.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct Tree<S: Eq + PartialEq> {
+    pub root: S,
+    pub nodes: Vec<tree::Node<S>>,
+}
+
+/// A section layout table, e.g. `.text section layout`.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct Section<S: Eq + PartialEq> {
+    pub name: SectionName<S>,
+    pub symbols: Vec<section_table::Symbol<S>>,
+}
+
+/// The tables of a whole map file, in the order they appear.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct MapFile<S: Eq + PartialEq> {
+    /// `None` if no line is specific to either dialect.
+    pub dialect: Option<Dialect>,
+    pub trees: Vec<Tree<S>>,
+    pub sections: Vec<Section<S>>,
+    pub memory: Vec<memory_table::Entry<S>>,
+    pub linker: Vec<linker_table::Entry<S>>,
+    pub mixed_mode_islands: Vec<Island<S>>,
+    pub branch_islands: Vec<Island<S>>,
+    /// The lines that lenient parsing could not parse, along with their
+    /// line numbers, starting at 0.
+    pub unknown: Vec<(usize, S)>,
+}
+
+impl<S: Eq + PartialEq> Default for MapFile<S> {
+    fn default() -> Self {
+        Self {
+            dialect: None,
+            trees: Vec::new(),
+            sections: Vec::new(),
+            memory: Vec::new(),
+            linker: Vec::new(),
+            mixed_mode_islands: Vec::new(),
+            branch_islands: Vec::new(),
+            unknown: Vec::new(),
+        }
+    }
+}
+
+impl<S: Eq + PartialEq> MapFile<S> {
+    pub fn map_str<T, F>(self, f: &mut F) -> MapFile<T>
+    where
+        T: Eq + PartialEq,
+        F: FnMut(S) -> T,
+    {
+        MapFile {
+            dialect: self.dialect,
+            trees: self
+                .trees
+                .into_iter()
+                .map(|tree| Tree {
+                    root: f(tree.root),
+                    nodes: tree
+                        .nodes
+                        .into_iter()
+                        .map(|node| node.map_str(f))
+                        .collect(),
+                })
+                .collect(),
+            sections: self
+                .sections
+                .into_iter()
+                .map(|section| Section {
+                    name: section.name.map_str(f),
+                    symbols: section
+                        .symbols
+                        .into_iter()
+                        .map(|symbol| symbol.map_str(f))
+                        .collect(),
+                })
+                .collect(),
+            memory: self
+                .memory
+                .into_iter()
+                .map(|entry| entry.map_str(f))
+                .collect(),
+            linker: self
+                .linker
+                .into_iter()
+                .map(|entry| entry.map_str(f))
+                .collect(),
+            mixed_mode_islands: self
+                .mixed_mode_islands
+                .into_iter()
+                .map(|island| island.map_str(f))
+                .collect(),
+            branch_islands: self
+                .branch_islands
+                .into_iter()
+                .map(|island| island.map_str(f))
+                .collect(),
+            unknown: self
+                .unknown
+                .into_iter()
+                .map(|(number, text)| (number, f(text)))
+                .collect(),
+        }
+    }
+
+    /// Appends the tables of a map that directly follows this one.
+    ///
+    /// The line numbers of unknown lines are kept as they are, i.e. relative
+    /// to the start of the map that they were parsed from.
+    pub fn append(&mut self, other: MapFile<S>) {
+        self.dialect = self.dialect.max(other.dialect);
+        self.trees.extend(other.trees);
+        self.sections.extend(other.sections);
+        self.memory.extend(other.memory);
+        self.linker.extend(other.linker);
+        self.mixed_mode_islands.extend(other.mixed_mode_islands);
+        self.branch_islands.extend(other.branch_islands);
+        self.unknown.extend(other.unknown);
+    }
+
+    /// Finds the section layout with the given name.
+    pub fn section(&self, name: &SectionName<S>) -> Option<&Section<S>> {
+        self.sections.iter().find(|section| &section.name == name)
+    }
+
+    /// Iterates over every section layout symbol along with the name of the
+    /// section it belongs to.
+    pub fn symbols(
+        &self,
+    ) -> impl Iterator<Item = (&SectionName<S>, &section_table::Symbol<S>)>
+    {
+        self.sections.iter().flat_map(|section| {
+            section
+                .symbols
+                .iter()
+                .map(move |symbol| (&section.name, symbol))
+        })
+    }
+
+    /// Finds the section layout symbol whose data contains `virt_addr`.
+    pub fn symbol_at(
+        &self,
+        virt_addr: u32,
+    ) -> Option<(&SectionName<S>, &section_table::Symbol<S>)> {
+        self.symbols()
+            .find(|(_, symbol)| symbol.contains(virt_addr))
+    }
+
+    /// Finds the branch island whose code contains `virt_addr`. Only
+    /// PowerPC branch islands have a known size, see [`Island::BRANCH_SIZE`],
+    /// so mixed mode islands and the islands of ARM maps are never found.
+    pub fn island_at(&self, virt_addr: u32) -> Option<&Island<S>> {
+        if self.dialect == Some(Dialect::Arm) {
+            return None;
+        }
+
+        self.branch_islands.iter().find(|island| {
+            let end =
+                island.virt_addr.saturating_add(Island::<S>::BRANCH_SIZE);
+            (island.virt_addr..end).contains(&virt_addr)
+        })
+    }
+}
+
+/// Assembles parsed lines into a [`MapFile`], keeping track of which table
+/// the following rows belong to.
+#[derive(Debug)]
+pub struct Builder<S: Eq + PartialEq> {
+    map_file: MapFile<S>,
+    context: Context,
+    /// The number of the next line.
+    line: usize,
+}
+
+impl<S: Eq + PartialEq> Default for Builder<S> {
+    fn default() -> Self {
+        Self {
+            map_file: MapFile::default(),
+            context: Context::None,
+            line: 0,
+        }
+    }
+}
+
+impl<S: Eq + PartialEq> Builder<S> {
+    /// Adds the next line of the map, failing if it is a table row that does
+    /// not follow its table's title.
+    pub fn push(&mut self, line: Line<S>) -> Result<(), &'static str> {
+        let map_file = &mut self.map_file;
+        let number = self.line;
+        self.line += 1;
+        map_file.dialect = map_file.dialect.max(line.dialect());
+        let unexpected = "Table row is outside of its table";
+
+        self.context = self.context.next(&line);
+        if !self.context.accepts(&line) {
+            return Err(unexpected);
+        }
+
+        match line {
+            Line::TreeTitle(root) => map_file.trees.push(Tree {
+                root,
+                nodes: Vec::new(),
+            }),
+            Line::TreeNode(node) => match map_file.trees.last_mut() {
+                Some(tree) => tree.nodes.push(node),
+                None => return Err(unexpected),
+            },
+            Line::SectionTitle(name) => map_file.sections.push(Section {
+                name,
+                symbols: Vec::new(),
+            }),
+            Line::SectionSymbol(symbol) => {
+                match map_file.sections.last_mut() {
+                    Some(section) => section.symbols.push(symbol),
+                    None => return Err(unexpected),
+                }
+            }
+            Line::MemoryEntry(entry) => map_file.memory.push(entry),
+            Line::LinkerEntry(entry) => map_file.linker.push(entry),
+            Line::MixedModeIsland(island) => {
+                map_file.mixed_mode_islands.push(island)
+            }
+            Line::BranchIsland(island) => map_file.branch_islands.push(island),
+            Line::Unknown(text) => map_file.unknown.push((number, text)),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    pub fn finish(self) -> MapFile<S> {
+        self.map_file
+    }
+}
+
+/// Parses a whole map file into its tables.
+pub(crate) fn map_file<'a, E>(
+    input: &'a str,
+) -> Result<MapFile<&'a str>, nom::Err<E>>
+where
+    E: ParseError<&'a str>
+        + FromExternalError<&'a str, ParseIntError>
+        + FromExternalError<&'a str, &'static str>
+        + ContextError<&'a str>,
+{
+    map_file_with(input, |s| s)
+}
+
+/// Parses a whole map file, converting every string through `f` as each line
+/// is parsed, e.g. to intern them with [`StringPool::intern`].
+pub(crate) fn map_file_with<'a, E, S, F>(
+    input: &'a str,
+    mut f: F,
+) -> Result<MapFile<S>, nom::Err<E>>
+where
+    E: ParseError<&'a str>
+        + FromExternalError<&'a str, ParseIntError>
+        + FromExternalError<&'a str, &'static str>
+        + ContextError<&'a str>,
+    S: Eq + PartialEq,
+    F: FnMut(&'a str) -> S,
+{
+    let mut builder = Builder::default();
+    let mut parser = LineParser::new();
+
+    for input in input.lines() {
+        let (_, line) = parser.line::<E>(input)?;
+        builder.push(line.map_str(&mut f)).map_err(|err| {
+            nom::Err::Error(E::from_external_error(
+                input,
+                ErrorKind::Verify,
+                err,
+            ))
+        })?;
+    }
+
+    Ok(builder.finish())
+}
+
+/// Parses a whole map file, skipping any lines that do not parse or that are
+/// outside of their table instead of failing, and returning an error for each
+/// of them.
+pub(crate) fn map_file_lenient<'a, E>(
+    input: &'a str,
+) -> (MapFile<&'a str>, Vec<nom::Err<E>>)
+where
+    E: ParseError<&'a str>
+        + FromExternalError<&'a str, ParseIntError>
+        + FromExternalError<&'a str, &'static str>
+        + ContextError<&'a str>,
+{
+    let mut builder = Builder::default();
+    let mut parser = LineParser::new();
+    let mut errors = Vec::new();
+
+    for input in input.lines() {
+        let (line, err) = parser.line_lenient::<E>(input);
+        errors.extend(err);
+        if let Err(err) = builder.push(line) {
+            errors.push(nom::Err::Error(E::from_external_error(
+                input,
+                ErrorKind::Verify,
+                err,
+            )));
+        }
+    }
+
+    (builder.finish(), errors)
+}
+
+/// Parses a whole map file in parallel, splitting it into chunks at each
+/// table title and stitching the parsed chunks back together in order.
+#[cfg(feature = "rayon")]
+pub(crate) fn par_map_file<'a, E>(
+    input: &'a str,
+) -> Result<MapFile<&'a str>, nom::Err<E>>
+where
+    E: ParseError<&'a str>
+        + FromExternalError<&'a str, ParseIntError>
+        + FromExternalError<&'a str, &'static str>
+        + ContextError<&'a str>
+        + Send,
+{
+    use rayon::prelude::*;
+
+    let index = MapIndex::new(input);
+    let chunks = index
+        .tables()
+        .par_iter()
+        .map(|table| index.map_file::<E>(table))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(chunks
+        .into_iter()
+        .fold(MapFile::default(), |mut map_file, chunk| {
+            map_file.append(chunk);
+            map_file
+        }))
+}
+
+/// Parses a whole map file, interning every string into `pool`.
+pub(crate) fn map_file_interned<'a, E>(
+    input: &'a str,
+    pool: &mut StringPool,
+) -> Result<MapFile<Handle>, nom::Err<E>>
+where
+    E: ParseError<&'a str>
+        + FromExternalError<&'a str, ParseIntError>
+        + FromExternalError<&'a str, &'static str>
+        + ContextError<&'a str>,
+{
+    map_file_with(input, |s| pool.intern(s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{map_file, map_file_lenient, MapFile, Section, Tree};
+    use crate::{
+        linker_table,
+        map::{Dialect, Identifier, Origin, SectionName},
+        memory_table, section_table,
+        tree::{self, Scope, Specifier, Type},
+        utils::test_utils::assert_diff,
+    };
+    use nom_supreme::error::ErrorTree;
+
+    #[test]
+    fn test_map_file() {
+        let input = "\
+Link map of __start\r\n\
+\x20 1] __start (func,global) found in __start.c.o \r\n\
+\r\n\
+\r\n\
+.init section layout\r\n\
+\x20 Starting        Virtual\r\n\
+\x20 address  Size   address\r\n\
+\x20 -----------------------\r\n\
+\x20 00000000 0000f0 80003100  4 __start\x20\t__start.c.o \r\n\
+\r\n\
+\r\n\
+Memory map:\r\n\
+\x20                  Starting Size     File\r\n\
+\x20                  address           Offset\r\n\
+\x20           .init  80003100 000000f0 000001c0\r\n\
+\r\n\
+\r\n\
+Linker generated symbols:\r\n\
+\x20          _db_stack_addr 804f0c00\r\n\
+";
+
+        let start = || Identifier::Named {
+            name: "__start",
+            instance: None,
+        };
+        let origin = || Origin {
+            obj: "__start.c.o",
+            src: None,
+            asm: false,
+        };
+
+        let expected = MapFile {
+            dialect: None,
+            trees: vec![Tree {
+                root: "__start",
+                nodes: vec![tree::Node {
+                    depth: 1,
+                    data: tree::Data::Object(
+                        start(),
+                        Specifier {
+                            r#type: Type::Function,
+                            scope: Scope::Global,
+                            origin: origin(),
+                        },
+                    ),
+                }],
+            }],
+            sections: vec![Section {
+                name: SectionName::Init,
+                symbols: vec![section_table::Symbol {
+                    addr: 0,
+                    virt_addr: 0x80003100,
+                    file_addr: None,
+                    data: section_table::Data::Parent {
+                        size: 0xf0,
+                        align: 4,
+                    },
+                    id: start(),
+                    origin: origin(),
+                    padding: 2,
+                }],
+            }],
+            memory: vec![memory_table::Entry {
+                data: memory_table::Data::Main {
+                    name: SectionName::Init,
+                    virt_addr: 0x80003100,
+                },
+                size: 0xf0,
+                file_addr: 0x1c0,
+                rom: None,
+                padding: 12,
+            }],
+            linker: vec![linker_table::Entry {
+                name: "_db_stack_addr",
+                virt_addr: 0x804f0c00,
+                padding: 11,
+            }],
+            mixed_mode_islands: vec![],
+            branch_islands: vec![],
+            unknown: vec![],
+        };
+
+        match map_file::<ErrorTree<&str>>(input) {
+            Ok(actual) => assert_diff(&expected, &actual),
+            Err(err) => panic!("{err:#?}"),
+        }
+
+        let orphan = "\x20 1] __start (func,global) found in __start.c.o \r\n";
+        assert!(map_file::<ErrorTree<&str>>(orphan).is_err());
+    }
+
+    #[test]
+    fn test_map_file_lenient() {
+        let input = "\
+Memory map:\r\n\
+\x20           .init  80003100 000000f0 000001c0\r\n\
+Unexpected linker message\r\n\
+\x20           .text  8000310 000000f0 000001c0\r\n\
+\x20           .data  80003200 00000010 000002c0\r\n\
+";
+
+        assert!(map_file::<ErrorTree<&str>>(input).is_err());
+        let (map_file, errors) = map_file_lenient::<ErrorTree<&str>>(input);
+        assert_eq!(errors.len(), 2);
+        let names = map_file
+            .memory
+            .iter()
+            .map(|entry| &entry.data)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                &memory_table::Data::Main {
+                    name: SectionName::Init,
+                    virt_addr: 0x80003100,
+                },
+                &memory_table::Data::Main {
+                    name: SectionName::Data,
+                    virt_addr: 0x80003200,
+                },
+            ]
+        );
+        assert_eq!(
+            map_file.unknown,
+            vec![
+                (2, "Unexpected linker message"),
+                (3, "            .text  8000310 000000f0 000001c0"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_overflowing_columns() {
+        let input = "\
+.bss section layout\r\n\
+\x20 00000000 1000000 80400000 32 lbl_80400000\x20\tmain.c.o \r\n\
+\r\n\
+Memory map:\r\n\
+\x20 .a_very_long_section_name  80003100 000000f0 000001c0\r\n\
+\x20 .debug_a_very_long_section_name 1000000 00000100\r\n\
+\r\n\
+Linker generated symbols:\r\n\
+\x20 _a_very_long_linker_generated_symbol 804f0c00\r\n\
+";
+
+        let map_file = map_file::<ErrorTree<&str>>(input).unwrap();
+        assert_eq!(
+            map_file.sections[0].symbols[0].data,
+            section_table::Data::Parent {
+                size: 0x1000000,
+                align: 32,
+            }
+        );
+        assert_eq!(map_file.memory[0].padding, 2);
+        assert_eq!(map_file.memory[1].size, 0x1000000);
+        assert_eq!(
+            map_file.linker[0].name,
+            "_a_very_long_linker_generated_symbol"
+        );
+    }
+
+    #[test]
+    fn test_island_at() {
+        let input = "\
+Branch Islands\r\n\
+\x20 80003110 branch island @1234 created for OSReport\r\n\
+";
+
+        let map_file = map_file::<ErrorTree<&str>>(input).unwrap();
+        let island = map_file.island_at(0x80003114).unwrap();
+        assert_eq!(
+            island.target,
+            Identifier::Named {
+                name: "OSReport",
+                instance: None,
+            }
+        );
+        assert!(map_file.island_at(0x80003100).is_none());
+        assert!(map_file.island_at(0x80003120).is_none());
+
+        let input = "\
+Mixed Mode Islands\r\n\
+\x20 02000100 mixed mode island __arm_island created for main\r\n\
+\r\n\
+Branch Islands\r\n\
+\x20 02000200 branch island __far_island created for main\r\n\
+";
+        let mut islands = super::map_file::<ErrorTree<&str>>(input).unwrap();
+        assert!(islands.island_at(0x02000100).is_none());
+        assert!(islands.island_at(0x02000200).is_some());
+        islands.dialect = Some(Dialect::Arm);
+        assert!(islands.island_at(0x02000200).is_none());
+    }
+
+    #[test]
+    fn test_arm_map_file() {
+        let input = "\
+.itcm section layout\r\n\
+\x20 Starting        Virtual  File\r\n\
+\x20 address  Size   address  offset\r\n\
+\x20 ---------------------------------\r\n\
+\x20 00000000 000040 01ff8000 00001000  4 .itcm\x20\tmain.o \r\n\
+\x20 00000000 000000 01ff8000 00001000 $t (entry of .itcm) \tmain.o \r\n\
+";
+
+        let map_file = map_file::<ErrorTree<&str>>(input).unwrap();
+        assert_eq!(map_file.dialect, Some(Dialect::Arm));
+        assert_eq!(map_file.sections[0].name, SectionName::Itcm);
+        assert_eq!(map_file.sections[0].symbols.len(), 2);
+    }
+
+    #[test]
+    fn test_dialect() {
+        let dialect = |rows: &str| {
+            let input = format!(
+                "\
+Memory map:\r\n\
+\x20                  Starting Size     File\r\n\
+\x20                  address           Offset\r\n\
+{rows}"
+            );
+            map_file::<ErrorTree<&str>>(&input).unwrap().dialect
+        };
+
+        let text = "\x20           .text  80003100 000000f0 000001c0\r\n";
+        let sdata2 = "\x20         .sdata2  80500000 00000010 00000300\r\n";
+        let itcm = "\x20           .itcm  01ff8000 00000040 00001000\r\n";
+        assert_eq!(dialect(text), None);
+        assert_eq!(dialect(&[text, sdata2].concat()), Some(Dialect::PowerPc));
+        assert_eq!(dialect(&[itcm, text].concat()), Some(Dialect::Arm));
+        assert_eq!(
+            dialect(&[itcm, sdata2, text].concat()),
+            Some(Dialect::Arm)
+        );
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_par_map_file() {
+        use super::par_map_file;
+
+        let input = "\
+Link map of __start\r\n\
+\x20 1] __start (func,global) found in __start.c.o \r\n\
+\r\n\
+.init section layout\r\n\
+\x20 00000000 0000f0 80003100  4 __start\x20\t__start.c.o \r\n\
+\r\n\
+.text section layout\r\n\
+\x20 00000000 000010 80003200  4 main\x20\tmain.c.o \r\n\
+\r\n\
+Memory map:\r\n\
+\x20           .init  80003100 000000f0 000001c0\r\n\
+\r\n\
+Linker generated symbols:\r\n\
+\x20          _db_stack_addr 804f0c00\r\n\
+";
+
+        assert_diff(
+            &map_file::<ErrorTree<&str>>(input).unwrap(),
+            &par_map_file::<ErrorTree<&str>>(input).unwrap(),
+        );
+    }
+}
@@ -1,34 +1,146 @@
-use crate::map::{
-    hex, padded, section_name, DebugSectionName, Line, SectionName,
+use crate::{
+    map::{
+        column, hex_digits, hex_min, section_name, DebugSectionName, Line,
+        SectionName,
+    },
+    span::{FieldKind, Fields},
 };
 use nom::{
     branch::alt,
-    bytes::complete::tag,
-    character::complete::char,
-    combinator::{eof, map, recognize},
-    error::{FromExternalError, ParseError},
+    bytes::complete::{tag, take_while1},
+    character::complete::{char, digit1, space0, space1},
+    combinator::{
+        all_consuming, consumed, eof, map, map_res, opt, recognize, rest,
+        verify,
+    },
+    error::{context, ContextError, ErrorKind, FromExternalError, ParseError},
     multi::count,
-    sequence::{preceded, terminated, tuple},
-    IResult, Parser,
+    sequence::{delimited, preceded, terminated, tuple},
+    IResult,
 };
-use std::num::ParseIntError;
+use std::{
+    fmt::{self, Display, Formatter},
+    num::ParseIntError,
+};
+
+/// The columns of a memory map, as announced by its column header.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Layout {
+    /// Virtual address, size and file offset.
+    #[default]
+    Standard,
+    /// Like [`Layout::Standard`], followed by the columns of [`Rom`].
+    Rom,
+}
 
-#[derive(Debug, Eq, PartialEq, Hash)]
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(serialize = "S: serde::Serialize + std::fmt::Display"))
+)]
 pub enum Data<S> {
     Main {
         name: SectionName<S>,
         virt_addr: u32,
     },
     Debug {
-        name: DebugSectionName,
+        name: DebugSectionName<S>,
     },
 }
 
-#[derive(Debug, Eq, PartialEq, Hash)]
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(serialize = "S: serde::Serialize + std::fmt::Display"))
+)]
 pub struct Entry<S> {
     pub data: Data<S>,
     pub size: u32,
     pub file_addr: u32,
+    /// Only present in the memory maps of ROM-based builds.
+    pub rom: Option<Rom<S>>,
+    /// The number of spaces before the name, which is right-aligned.
+    pub padding: usize,
+}
+
+/// The extra columns of the memory map of a ROM-based build, which the
+/// embedded PowerPC linkers emit when generating S-records or binary files.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Rom<S> {
+    pub rom_addr: u32,
+    pub ram_buffer_addr: u32,
+    pub s_record_line: u32,
+    pub bin_file_addr: u32,
+    pub bin_file_name: S,
+}
+
+impl<S> Entry<S> {
+    pub fn map_str<T, F>(self, f: &mut F) -> Entry<T>
+    where
+        F: FnMut(S) -> T,
+    {
+        Entry {
+            data: match self.data {
+                Data::Main { name, virt_addr } => Data::Main {
+                    name: name.map_str(f),
+                    virt_addr,
+                },
+                Data::Debug { name } => Data::Debug {
+                    name: name.map_str(f),
+                },
+            },
+            size: self.size,
+            file_addr: self.file_addr,
+            rom: self.rom.map(|rom| Rom {
+                rom_addr: rom.rom_addr,
+                ram_buffer_addr: rom.ram_buffer_addr,
+                s_record_line: rom.s_record_line,
+                bin_file_addr: rom.bin_file_addr,
+                bin_file_name: f(rom.bin_file_name),
+            }),
+            padding: self.padding,
+        }
+    }
+}
+
+impl<S: Display> Display for Entry<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:1$}", "", self.padding)?;
+        match &self.data {
+            Data::Main { name, virt_addr } => write!(
+                f,
+                "{name}  {virt_addr:08x} {:08x} {:08x}",
+                self.size, self.file_addr
+            )?,
+            Data::Debug { name } => write!(
+                f,
+                "{name}           {:06x} {:08x}",
+                self.size, self.file_addr
+            )?,
+        }
+        match &self.rom {
+            Some(rom) => write!(f, " {rom}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<S: Display> Display for Rom<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:08x} {:08x} {:>11} {:08x} {}",
+            self.rom_addr,
+            self.ram_buffer_addr,
+            self.s_record_line,
+            self.bin_file_addr,
+            self.bin_file_name
+        )
+    }
 }
 
 pub fn title<'a, E>(input: &'a str) -> IResult<&'a str, &'a str, E>
@@ -50,112 +162,299 @@ where
             tag("Size"),
             count(char(' '), 5),
             tag("File"),
+            opt(tuple((
+                space1,
+                tag("ROM"),
+                space1,
+                tag("RAM Buffer"),
+                space1,
+                tag("S-Record"),
+                space1,
+                tag("Bin File"),
+                space1,
+                tag("Bin File"),
+            ))),
         )),
-        |_| Line::MemoryColumns0,
+        |(_, _, _, _, _, _, rom)| {
+            Line::MemoryColumns0(match rom {
+                Some(_) => Layout::Rom,
+                None => Layout::Standard,
+            })
+        },
     )(input)
 }
 
+/// Parses the second line of the column header of the standard layout.
+///
+/// Kept for compatibility, see [`parsers`](crate::parsers).
 pub fn columns1<'a, E>(input: &'a str) -> IResult<&'a str, Line<&'a str>, E>
 where
     E: ParseError<&'a str>,
 {
+    columns1_for(Layout::Standard)(input)
+}
+
+pub(crate) fn columns1_for<'a, E>(
+    layout: Layout,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Line<&'a str>, E>
+where
+    E: ParseError<&'a str>,
+{
+    let rom = move |input| match layout {
+        Layout::Standard => Ok((input, ())),
+        Layout::Rom => map(
+            tuple((
+                space1,
+                tag("Address"),
+                space1,
+                tag("Address"),
+                space1,
+                tag("Line"),
+                space1,
+                tag("Offset"),
+                space1,
+                tag("Name"),
+            )),
+            |_| (),
+        )(input),
+    };
     map(
         tuple((
             count(char(' '), 19),
             tag("address"),
             count(char(' '), 11),
             tag("Offset"),
+            rom,
         )),
         |_| Line::MemoryColumns1,
-    )(input)
+    )
 }
 
+/// Parses a row of a table with the standard layout.
+///
+/// Kept for compatibility, see [`parsers`](crate::parsers).
 pub fn entry<'a, E>(input: &'a str) -> IResult<&'a str, Entry<&'a str>, E>
 where
     E: ParseError<&'a str>
         + FromExternalError<&'a str, ParseIntError>
         + FromExternalError<&'a str, &'static str>,
 {
-    map(
-        tuple((
-            terminated(padded(17).and_then(section_name), count(char(' '), 2)),
-            terminated(hex(8), char(' ')),
-            terminated(hex(8), char(' ')),
-            hex(8),
-        )),
-        |(name, virt_addr, size, file_addr)| Entry {
-            data: Data::Main { name, virt_addr },
-            size,
-            file_addr,
-        },
-    )(input)
+    crate::parsers::memory_table::entry(Layout::Standard)(input)
 }
 
-fn debug_section_name<'a, E>(
+/// Parses a row of a debug section, which [`entry`] accepts as well.
+pub fn debug_entry<'a, E>(
     input: &'a str,
-) -> IResult<&'a str, DebugSectionName, E>
+) -> IResult<&'a str, Entry<&'a str>, E>
 where
-    E: ParseError<&'a str>,
+    E: ParseError<&'a str>
+        + FromExternalError<&'a str, ParseIntError>
+        + FromExternalError<&'a str, &'static str>,
 {
-    use DebugSectionName::*;
+    verify(entry, |entry: &Entry<&str>| {
+        matches!(entry.data, Data::Debug { .. })
+    })(input)
+}
 
-    preceded(
-        char('.'),
-        alt((
-            map(tag("line"), |_| Line),
-            preceded(
-                tag("debug"),
-                alt((
-                    map(eof, |_| Main),
-                    preceded(
-                        char('_'),
-                        alt((
-                            map(tag("abbrev"), |_| Abbrev),
-                            map(tag("aranges"), |_| Aranges),
-                            map(tag("info"), |_| Info),
-                            map(tag("line"), |_| Info),
-                            map(tag("sfnames"), |_| SfNames),
-                            map(tag("srcinfo"), |_| SrcInfo),
-                            map(tag("str"), |_| Str),
-                        )),
-                    ),
+/// Parses a row of a table with the given layout, as announced by
+/// [`columns0`].
+///
+/// Debug sections have no virtual address, so their rows have one hex
+/// column fewer, which decides whether the name is that of a section or of
+/// a debug section. They never have the columns of ROM-based builds.
+pub(crate) fn entry_for<'a, E>(
+    layout: Layout,
+) -> impl FnMut(&'a str) -> IResult<&'a str, (Entry<&'a str>, Fields<'a>), E>
+where
+    E: ParseError<&'a str>
+        + FromExternalError<&'a str, ParseIntError>
+        + FromExternalError<&'a str, &'static str>
+        + ContextError<&'a str>,
+{
+    context("memory map entry", move |input: &'a str| {
+        let (input, ((padding, name), (first, addr_or_size), second, third)) =
+            tuple((
+                terminated(column(rest), space1),
+                consumed(hex_min(6)),
+                preceded(space1, consumed(hex_digits(8))),
+                opt(preceded(
+                    space1,
+                    context("file offset", consumed(hex_digits(8))),
                 )),
-            ),
-        )),
-    )(input)
+            ))(input)?;
+        let fields = Fields::new().with(FieldKind::SectionName, name);
+
+        let Some((file_text, file_addr)) = third else {
+            let (_, name) = context("section name", debug_section_name)(name)?;
+            let entry = Entry {
+                data: Data::Debug { name },
+                size: addr_or_size,
+                file_addr: second.1,
+                rom: None,
+                padding,
+            };
+            let fields = fields
+                .with(FieldKind::Size, first)
+                .with(FieldKind::FileAddr, second.0);
+            return Ok((input, (entry, fields)));
+        };
+
+        if first.len() != 8 {
+            return Err(nom::Err::Error(E::add_context(
+                first,
+                "virtual address",
+                E::from_external_error(
+                    first,
+                    ErrorKind::HexDigit,
+                    "expected 8 hex digits",
+                ),
+            )));
+        }
+        let (_, name) = context("section name", section_name)(name)?;
+        let fields = fields
+            .with(FieldKind::VirtAddr, first)
+            .with(FieldKind::Size, second.0)
+            .with(FieldKind::FileAddr, file_text);
+        let (input, (rom, fields)) = match layout {
+            Layout::Standard => (input, (None, fields)),
+            Layout::Rom => {
+                let (input, (rom, rom_fields)) = rom(input)?;
+                (input, (Some(rom), fields.chain(rom_fields)))
+            }
+        };
+
+        let entry = Entry {
+            data: Data::Main {
+                name,
+                virt_addr: addr_or_size,
+            },
+            size: second.1,
+            file_addr,
+            rom,
+            padding,
+        };
+        Ok((input, (entry, fields)))
+    })
 }
 
-pub fn debug_entry<'a, E>(
+fn rom<'a, E>(
     input: &'a str,
-) -> IResult<&'a str, Entry<&'a str>, E>
+) -> IResult<&'a str, (Rom<&'a str>, Fields<'a>), E>
 where
     E: ParseError<&'a str>
         + FromExternalError<&'a str, ParseIntError>
-        + FromExternalError<&'a str, &'static str>,
+        + FromExternalError<&'a str, &'static str>
+        + ContextError<&'a str>,
 {
     map(
         tuple((
-            terminated(
-                padded(17).and_then(debug_section_name),
-                count(char(' '), 11),
+            preceded(space1, context("ROM address", consumed(hex_digits(8)))),
+            preceded(
+                space1,
+                context("RAM buffer address", consumed(hex_digits(8))),
+            ),
+            preceded(
+                space1,
+                context(
+                    "S-record line",
+                    consumed(map_res(digit1, str::parse::<u32>)),
+                ),
+            ),
+            preceded(
+                space1,
+                context("bin file offset", consumed(hex_digits(8))),
+            ),
+            delimited(
+                space1,
+                context(
+                    "bin file name",
+                    take_while1(|c: char| !c.is_whitespace()),
+                ),
+                space0,
             ),
-            terminated(hex(6), char(' ')),
-            hex(8),
         )),
-        |(name, size, file_addr)| Entry {
-            data: Data::Debug { name },
-            size,
-            file_addr,
+        |(
+            (rom_text, rom_addr),
+            (ram_buffer_text, ram_buffer_addr),
+            (s_record_text, s_record_line),
+            (bin_file_text, bin_file_addr),
+            bin_file_name,
+        )| {
+            let fields = Fields::new()
+                .with(FieldKind::RomAddr, rom_text)
+                .with(FieldKind::RamBufferAddr, ram_buffer_text)
+                .with(FieldKind::SRecordLine, s_record_text)
+                .with(FieldKind::BinFileAddr, bin_file_text)
+                .with(FieldKind::BinFileName, bin_file_name);
+            let rom = Rom {
+                rom_addr,
+                ram_buffer_addr,
+                s_record_line,
+                bin_file_addr,
+                bin_file_name,
+            };
+            (rom, fields)
         },
     )(input)
 }
 
+fn debug_section_name<'a, E>(
+    input: &'a str,
+) -> IResult<&'a str, DebugSectionName<&'a str>, E>
+where
+    E: ParseError<&'a str>,
+{
+    use DebugSectionName::*;
+
+    alt((
+        all_consuming(preceded(
+            char('.'),
+            alt((
+                map(tag("line"), |_| Line),
+                map(tag("comment"), |_| Comment),
+                map(preceded(tag("mwcats"), opt(section_name)), MwCats),
+                preceded(
+                    tag("debug"),
+                    alt((
+                        map(eof, |_| Main),
+                        preceded(
+                            char('_'),
+                            alt((
+                                map(tag("abbrev"), |_| Abbrev),
+                                map(tag("aranges"), |_| Aranges),
+                                map(tag("frame"), |_| Frame),
+                                map(tag("info"), |_| Info),
+                                map(tag("line"), |_| DebugLine),
+                                map(tag("loc"), |_| Loc),
+                                map(tag("macinfo"), |_| MacInfo),
+                                map(tag("pubnames"), |_| PubNames),
+                                map(tag("pubtypes"), |_| PubTypes),
+                                map(tag("ranges"), |_| Ranges),
+                                map(tag("sfnames"), |_| SfNames),
+                                map(tag("srcinfo"), |_| SrcInfo),
+                                map(tag("str"), |_| Str),
+                            )),
+                        ),
+                    )),
+                ),
+            )),
+        )),
+        map(
+            recognize(preceded(
+                char('.'),
+                take_while1(|c: char| !c.is_whitespace()),
+            )),
+            Unknown,
+        ),
+    ))(input)
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{title, Data, Entry};
+    use super::{title, Data, Entry, Layout, Rom};
     use crate::{
         map::{DebugSectionName, SectionName},
-        memory_table::{columns0, columns1, debug_entry, entry},
+        memory_table::{columns0, columns1_for, entry_for},
         utils::test_utils::assert_diff,
     };
     use nom::{
@@ -179,13 +478,18 @@ Memory map:\r\n\
 \x20  .debug_sfnames           000000 00000000\r\n\
 \x20          .debug           000000 00000000\r\n\
 \x20           .line           000000 00000000\r\n\
+\x20     .debug_line           0001a0 00000100\r\n\
+\x20    .debug_frame           000020 000002a0\r\n\
+\x20    .mwcats.text           000040 000002c0\r\n\
+\x20        .comment           000010 00000300\r\n\
+\x20    .debug_names           000008 00000310\r\n\
 "
         .split_terminator("\r\n")
         .collect::<Vec<_>>();
 
         let expected: Vec<Line<&str>> = vec![
             Line::MemoryTitle,
-            Line::MemoryColumns0,
+            Line::MemoryColumns0(Layout::Standard),
             Line::MemoryColumns1,
             Line::MemoryEntry(Entry {
                 data: Data::Main {
@@ -194,6 +498,8 @@ Memory map:\r\n\
                 },
                 size: 0x23a8,
                 file_addr: 0x1c0,
+                rom: None,
+                padding: 12,
             }),
             Line::MemoryEntry(Entry {
                 data: Data::Main {
@@ -202,6 +508,8 @@ Memory map:\r\n\
                 },
                 size: 0x6a8,
                 file_addr: 0x2580,
+                rom: None,
+                padding: 11,
             }),
             Line::MemoryEntry(Entry {
                 data: Data::Main {
@@ -210,6 +518,8 @@ Memory map:\r\n\
                 },
                 size: 0xa1c,
                 file_addr: 0x2c40,
+                rom: None,
+                padding: 6,
             }),
             Line::MemoryEntry(Entry {
                 data: Data::Debug {
@@ -217,6 +527,8 @@ Memory map:\r\n\
                 },
                 size: 0,
                 file_addr: 0,
+                rom: None,
+                padding: 3,
             }),
             Line::MemoryEntry(Entry {
                 data: Data::Debug {
@@ -224,6 +536,8 @@ Memory map:\r\n\
                 },
                 size: 0,
                 file_addr: 0,
+                rom: None,
+                padding: 3,
             }),
             Line::MemoryEntry(Entry {
                 data: Data::Debug {
@@ -231,6 +545,8 @@ Memory map:\r\n\
                 },
                 size: 0,
                 file_addr: 0,
+                rom: None,
+                padding: 11,
             }),
             Line::MemoryEntry(Entry {
                 data: Data::Debug {
@@ -238,14 +554,63 @@ Memory map:\r\n\
                 },
                 size: 0,
                 file_addr: 0,
+                rom: None,
+                padding: 12,
+            }),
+            Line::MemoryEntry(Entry {
+                data: Data::Debug {
+                    name: DebugSectionName::DebugLine,
+                },
+                size: 0x1a0,
+                file_addr: 0x100,
+                rom: None,
+                padding: 6,
+            }),
+            Line::MemoryEntry(Entry {
+                data: Data::Debug {
+                    name: DebugSectionName::Frame,
+                },
+                size: 0x20,
+                file_addr: 0x2a0,
+                rom: None,
+                padding: 5,
+            }),
+            Line::MemoryEntry(Entry {
+                data: Data::Debug {
+                    name: DebugSectionName::MwCats(Some(SectionName::Text)),
+                },
+                size: 0x40,
+                file_addr: 0x2c0,
+                rom: None,
+                padding: 5,
+            }),
+            Line::MemoryEntry(Entry {
+                data: Data::Debug {
+                    name: DebugSectionName::Comment,
+                },
+                size: 0x10,
+                file_addr: 0x300,
+                rom: None,
+                padding: 9,
+            }),
+            Line::MemoryEntry(Entry {
+                data: Data::Debug {
+                    name: DebugSectionName::Unknown(".debug_names"),
+                },
+                size: 0x8,
+                file_addr: 0x310,
+                rom: None,
+                padding: 5,
             }),
         ];
 
         let mut parser = alt::<_, _, ErrorTree<&str>, _>((
             all_consuming(map(title, |_| Line::MemoryTitle)),
-            all_consuming(map(columns0, |_| Line::MemoryColumns0)),
-            all_consuming(map(columns1, |_| Line::MemoryColumns1)),
-            all_consuming(map(alt((entry, debug_entry)), Line::MemoryEntry)),
+            all_consuming(columns0),
+            all_consuming(columns1_for(Layout::Standard)),
+            all_consuming(map(entry_for(Layout::Standard), |(entry, _)| {
+                Line::MemoryEntry(entry)
+            })),
         ));
 
         let (input_len, expected_len) = (&input.len(), &expected.len());
@@ -261,4 +626,80 @@ Memory map:\r\n\
 
         assert_eq!(input_len, expected_len);
     }
+
+    #[test]
+    fn test_rom_memory_table() {
+        use crate::map::{Line, LineParser};
+
+        let input = "\
+Memory map:\r\n\
+\x20                  Starting Size     File     ROM      RAM Buffer  S-Record Bin File Bin File\r\n\
+\x20                  address           Offset   Address  Address     Line     Offset   Name\r\n\
+\x20           .init  ffe00000 00000318 00000200 fff00000 ffe00000           2 00000000 rom.bin\r\n\
+\x20           .text  00003100 000023a8 00000518 fff00318 00003100          14 00000318 rom.bin\r\n\
+";
+
+        let mut parser = LineParser::new();
+        let lines = input
+            .lines()
+            .map(|line| parser.parse(line))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(lines[1], Line::MemoryColumns0(Layout::Rom));
+        assert_eq!(lines[2], Line::MemoryColumns1);
+        assert_diff(
+            &lines[4],
+            &Line::MemoryEntry(Entry {
+                data: Data::Main {
+                    name: SectionName::Text,
+                    virt_addr: 0x3100,
+                },
+                size: 0x23a8,
+                file_addr: 0x518,
+                rom: Some(Rom {
+                    rom_addr: 0xfff00318,
+                    ram_buffer_addr: 0x3100,
+                    s_record_line: 14,
+                    bin_file_addr: 0x318,
+                    bin_file_name: "rom.bin",
+                }),
+                padding: 12,
+            }),
+        );
+    }
+
+    #[test]
+    fn test_layout_mismatch() {
+        use crate::map::LineParser;
+
+        let standard = "\
+Memory map:\r\n\
+\x20                  Starting Size     File\r\n\
+\x20                  address           Offset\r\n\
+";
+        let rom = "\
+Memory map:\r\n\
+\x20                  Starting Size     File     ROM      RAM Buffer  S-Record Bin File Bin File\r\n\
+\x20                  address           Offset   Address  Address     Line     Offset   Name\r\n\
+";
+        let standard_row = "            .init  80003100 000023a8 000001c0";
+        let rom_row = "            .init  ffe00000 00000318 00000200 fff00000 ffe00000           2 00000000 rom.bin";
+        let debug_row = "     .debug_line           0001a0 00000100";
+
+        for (header, row, ok) in [
+            (standard, standard_row, true),
+            (standard, rom_row, false),
+            (standard, debug_row, true),
+            (rom, standard_row, false),
+            (rom, rom_row, true),
+            (rom, debug_row, true),
+        ] {
+            let mut parser = LineParser::new();
+            for line in header.lines() {
+                parser.parse(line).unwrap();
+            }
+            assert_eq!(parser.parse(row).is_ok(), ok, "{row}");
+        }
+    }
 }
@@ -0,0 +1,118 @@
+//! Newline-delimited JSON output, built with the `ndjson` feature, e.g. for
+//! piping into `jq`.
+//!
+//! Each line of a map is written as one JSON object as soon as it is parsed,
+//! tagged with its [`Line`] variant and its 1-based line number:
+//!
+//! ```text
+//! {"line":2,"type":"SectionSymbol","data":{"addr":0,"virt_addr":2147496192,...}}
+//! ```
+//!
+//! Only one line is held in memory at a time, so maps of any size can be
+//! streamed. Lines that do not parse are written as `Unknown` along with an
+//! `error` rather than ending the stream.
+
+use crate::map::{Line, LineParser};
+use serde::Serialize;
+use std::io::{self, BufRead, Write};
+
+#[derive(Serialize)]
+struct Event<'a> {
+    line: usize,
+    #[serde(flatten)]
+    data: &'a Line<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<EventError<'a>>,
+}
+
+#[derive(Serialize)]
+struct EventError<'a> {
+    message: &'a str,
+    /// The 1-based column, counted in characters.
+    column: usize,
+    attempted: &'a [&'static str],
+}
+
+/// Parses the map read from `reader` line by line, writing an event for each
+/// line to `writer`.
+///
+/// Invalid UTF-8 is replaced rather than failing, so that the rest of the map
+/// is still parsed.
+pub fn write_events<R, W>(mut reader: R, mut writer: W) -> io::Result<()>
+where
+    R: BufRead,
+    W: Write,
+{
+    let mut parser = LineParser::new();
+    let mut buf = Vec::new();
+    let mut number = 0;
+
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            break;
+        }
+        number += 1;
+
+        // Strip the line ending the same way as `str::lines`.
+        let text = String::from_utf8_lossy(&buf);
+        let input = match text.strip_suffix('\n') {
+            Some(input) => input.strip_suffix('\r').unwrap_or(input),
+            None => &text,
+        };
+
+        let (line, err) = parser.parse_lenient(input);
+        let event = Event {
+            line: number,
+            data: &line,
+            error: err.as_ref().map(|err| EventError {
+                message: err.message(),
+                column: err.column(),
+                attempted: err.attempted(),
+            }),
+        };
+
+        serde_json::to_writer(&mut writer, &event)?;
+        writer.write_all(b"\n")?;
+    }
+
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write_events;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_write_events() {
+        let input = "\
+.text section layout\r\n\
+\x20 00000000 000010 80003100  4 __start\x20\t__start.c.o \r\n\
+\x20 nope\r\n\
+\r\n\
+Memory map:";
+
+        let mut buf = Vec::new();
+        write_events(input.as_bytes(), &mut buf).unwrap();
+        let events = String::from_utf8(buf)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect::<Vec<Value>>();
+
+        assert_eq!(events.len(), 5);
+        assert_eq!(
+            events[0],
+            json!({"line": 1, "type": "SectionTitle", "data": ".text"})
+        );
+        assert_eq!(events[1]["type"], "SectionSymbol");
+        assert_eq!(events[1]["data"]["virt_addr"], 0x80003100u32);
+        assert_eq!(events[1]["data"]["id"], "__start");
+        assert_eq!(events[2]["type"], "Unknown");
+        assert_eq!(events[2]["data"], "  nope");
+        assert!(events[2]["error"]["message"].is_string());
+        assert_eq!(events[3], json!({"line": 4, "type": "Empty"}));
+        assert_eq!(events[4], json!({"line": 5, "type": "MemoryTitle"}));
+    }
+}
@@ -0,0 +1,378 @@
+//! The nom combinators that the rest of the crate is built on.
+//!
+//! These expose nom's `IResult` and error traits, and so change whenever nom
+//! does. Prefer the `parse_*` functions at the crate root, which only return
+//! the crate's own [`Error`](crate::Error).
+
+use crate::{
+    map::{self, Line},
+    span::Fields,
+};
+use nom::{
+    error::{ContextError, ErrorKind, FromExternalError, ParseError},
+    IResult,
+};
+use std::{marker::PhantomData, num::ParseIntError};
+
+pub use crate::{
+    map::{c_name, cpp_name, identifier, origin, padded, section_name},
+    windows::filename,
+};
+
+/// Wraps a caller's error type, which may not be able to hold everything that
+/// the crate's parsers attach to their errors, and drops what `D` names.
+struct Adapter<E, D>(E, PhantomData<D>);
+
+/// Drops the names of the rows and fields that the crate's parsers attach.
+enum Context {}
+
+/// Like [`Context`], but also drops the crate's error messages, e.g.
+/// `expected 8 hex digits`, keeping only their [`ErrorKind`].
+enum Messages {}
+
+impl<E, D> Adapter<E, D> {
+    fn new(err: E) -> Self {
+        Self(err, PhantomData)
+    }
+}
+
+impl<I, E: ParseError<I>, D> ParseError<I> for Adapter<E, D> {
+    fn from_error_kind(input: I, kind: ErrorKind) -> Self {
+        Self::new(E::from_error_kind(input, kind))
+    }
+
+    fn append(input: I, kind: ErrorKind, other: Self) -> Self {
+        Self::new(E::append(input, kind, other.0))
+    }
+
+    fn from_char(input: I, c: char) -> Self {
+        Self::new(E::from_char(input, c))
+    }
+
+    fn or(self, other: Self) -> Self {
+        Self::new(self.0.or(other.0))
+    }
+}
+
+impl<I, E, D> ContextError<I> for Adapter<E, D> {}
+
+impl<I, X, E: FromExternalError<I, X>> FromExternalError<I, X>
+    for Adapter<E, Context>
+{
+    fn from_external_error(input: I, kind: ErrorKind, e: X) -> Self {
+        Self::new(E::from_external_error(input, kind, e))
+    }
+}
+
+impl<I, E> FromExternalError<I, ParseIntError> for Adapter<E, Messages>
+where
+    E: FromExternalError<I, ParseIntError>,
+{
+    fn from_external_error(
+        input: I,
+        kind: ErrorKind,
+        e: ParseIntError,
+    ) -> Self {
+        Self::new(E::from_external_error(input, kind, e))
+    }
+}
+
+impl<I, E: ParseError<I>> FromExternalError<I, &'static str>
+    for Adapter<E, Messages>
+{
+    fn from_external_error(
+        input: I,
+        kind: ErrorKind,
+        _: &'static str,
+    ) -> Self {
+        Self::new(E::from_error_kind(input, kind))
+    }
+}
+
+fn adapted<T, E, D>(
+    result: Result<T, nom::Err<Adapter<E, D>>>,
+) -> Result<T, nom::Err<E>> {
+    result.map_err(|err| err.map(|Adapter(err, _)| err))
+}
+
+/// Drops the fields that the crate's row parsers locate along with a row.
+fn unlocated<I, T, E>(
+    result: IResult<I, (T, Fields<'_>), E>,
+) -> IResult<I, T, E> {
+    result.map(|(input, (row, _))| (input, row))
+}
+
+/// Parses a single line without any context, trying the parsers of every
+/// kind of row in turn.
+pub fn line<'a, E>(input: &'a str) -> IResult<&'a str, Line<&'a str>, E>
+where
+    E: ParseError<&'a str>
+        + FromExternalError<&'a str, ParseIntError>
+        + FromExternalError<&'a str, &'static str>,
+{
+    adapted(map::any_line::<Adapter<E, Context>>(input))
+}
+
+/// Parses exactly `count` hex digits.
+pub fn hex<'a, E>(
+    count: usize,
+) -> impl FnMut(&'a str) -> IResult<&'a str, u32, E>
+where
+    E: ParseError<&'a str> + FromExternalError<&'a str, ParseIntError>,
+{
+    let mut parser = map::hex_digits::<Adapter<E, Messages>>(count);
+    move |input| adapted(parser(input))
+}
+
+pub mod tree {
+    use super::{adapted, unlocated, Adapter, Context};
+    use crate::tree::{self, Node};
+    use nom::{
+        error::{FromExternalError, ParseError},
+        IResult,
+    };
+    use std::num::ParseIntError;
+
+    pub use crate::tree::title;
+
+    pub fn node<'a, E>(input: &'a str) -> IResult<&'a str, Node<&'a str>, E>
+    where
+        E: ParseError<&'a str> + FromExternalError<&'a str, ParseIntError>,
+    {
+        adapted(unlocated(tree::node_located::<Adapter<E, Context>>(input)))
+    }
+}
+
+pub mod section_table {
+    use super::{adapted, unlocated, Adapter, Context};
+    use crate::{
+        map::{Line, SectionName},
+        section_table::{self, Layout, Symbol},
+    };
+    use nom::{
+        error::{FromExternalError, ParseError},
+        IResult,
+    };
+    use std::num::ParseIntError;
+
+    pub use crate::section_table::columns0;
+
+    pub fn title<'a, E>(
+        input: &'a str,
+    ) -> IResult<&'a str, SectionName<&'a str>, E>
+    where
+        E: ParseError<&'a str>,
+    {
+        unlocated(section_table::title_located(input))
+    }
+
+    pub fn columns1<'a, E>(
+        layout: Layout,
+    ) -> impl FnMut(&'a str) -> IResult<&'a str, Line<&'a str>, E>
+    where
+        E: ParseError<&'a str>,
+    {
+        section_table::columns1_for(layout)
+    }
+
+    pub fn separator<'a, E>(
+        layout: Layout,
+    ) -> impl FnMut(&'a str) -> IResult<&'a str, Line<&'a str>, E>
+    where
+        E: ParseError<&'a str>,
+    {
+        section_table::separator_for(layout)
+    }
+
+    /// Parses a row of a table with the given layout, as announced by
+    /// [`columns0`].
+    pub fn symbol<'a, E>(
+        layout: Layout,
+    ) -> impl FnMut(&'a str) -> IResult<&'a str, Symbol<&'a str>, E>
+    where
+        E: ParseError<&'a str>
+            + FromExternalError<&'a str, ParseIntError>
+            + FromExternalError<&'a str, &'static str>,
+    {
+        let mut parser =
+            section_table::symbol_for::<Adapter<E, Context>>(layout);
+        move |input| adapted(unlocated(parser(input)))
+    }
+}
+
+pub mod memory_table {
+    use super::{adapted, unlocated, Adapter, Context};
+    use crate::{
+        map::Line,
+        memory_table::{self, Entry, Layout},
+    };
+    use nom::{
+        error::{FromExternalError, ParseError},
+        IResult,
+    };
+    use std::num::ParseIntError;
+
+    pub use crate::memory_table::{columns0, title};
+
+    pub fn columns1<'a, E>(
+        layout: Layout,
+    ) -> impl FnMut(&'a str) -> IResult<&'a str, Line<&'a str>, E>
+    where
+        E: ParseError<&'a str>,
+    {
+        memory_table::columns1_for(layout)
+    }
+
+    /// Parses a row of a table with the given layout, as announced by
+    /// [`columns0`]. Rows of debug sections are accepted in either layout.
+    pub fn entry<'a, E>(
+        layout: Layout,
+    ) -> impl FnMut(&'a str) -> IResult<&'a str, Entry<&'a str>, E>
+    where
+        E: ParseError<&'a str>
+            + FromExternalError<&'a str, ParseIntError>
+            + FromExternalError<&'a str, &'static str>,
+    {
+        let mut parser =
+            memory_table::entry_for::<Adapter<E, Context>>(layout);
+        move |input| adapted(unlocated(parser(input)))
+    }
+}
+
+pub mod linker_table {
+    use super::{adapted, unlocated, Adapter, Context};
+    use crate::linker_table::{self, Entry};
+    use nom::{
+        error::{FromExternalError, ParseError},
+        IResult,
+    };
+    use std::num::ParseIntError;
+
+    pub use crate::linker_table::title;
+
+    pub fn entry<'a, E>(input: &'a str) -> IResult<&'a str, Entry<&'a str>, E>
+    where
+        E: ParseError<&'a str>
+            + FromExternalError<&'a str, ParseIntError>
+            + FromExternalError<&'a str, &'static str>,
+    {
+        adapted(unlocated(
+            linker_table::entry_located::<Adapter<E, Context>>(input),
+        ))
+    }
+}
+
+pub mod island_table {
+    use super::{adapted, unlocated, Adapter, Context};
+    use crate::island_table::{self, Island};
+    use nom::{
+        error::{FromExternalError, ParseError},
+        IResult,
+    };
+    use std::num::ParseIntError;
+
+    pub use crate::island_table::{branch_title, mixed_mode_title};
+
+    pub fn mixed_mode_entry<'a, E>(
+        input: &'a str,
+    ) -> IResult<&'a str, Island<&'a str>, E>
+    where
+        E: ParseError<&'a str>
+            + FromExternalError<&'a str, ParseIntError>
+            + FromExternalError<&'a str, &'static str>,
+    {
+        adapted(unlocated(island_table::mixed_mode_entry::<
+            Adapter<E, Context>,
+        >(input)))
+    }
+
+    pub fn branch_entry<'a, E>(
+        input: &'a str,
+    ) -> IResult<&'a str, Island<&'a str>, E>
+    where
+        E: ParseError<&'a str>
+            + FromExternalError<&'a str, ParseIntError>
+            + FromExternalError<&'a str, &'static str>,
+    {
+        adapted(unlocated(
+            island_table::branch_entry::<Adapter<E, Context>>(input),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hex, line, section_table};
+    use crate::{map::Line, section_table::Layout};
+    use nom::error::{ErrorKind, FromExternalError, ParseError};
+    use std::num::ParseIntError;
+
+    /// An error type with only the bounds that the parsers had originally.
+    #[derive(Debug, PartialEq)]
+    struct Kind(ErrorKind);
+
+    impl<I> ParseError<I> for Kind {
+        fn from_error_kind(_: I, kind: ErrorKind) -> Self {
+            Self(kind)
+        }
+
+        fn append(_: I, _: ErrorKind, other: Self) -> Self {
+            other
+        }
+    }
+
+    impl<I, X> FromExternalError<I, X> for Kind {
+        fn from_external_error(_: I, kind: ErrorKind, _: X) -> Self {
+            Self(kind)
+        }
+    }
+
+    #[test]
+    fn test_custom_error() {
+        assert_eq!(line::<Kind>("Memory map:"), Ok(("", Line::MemoryTitle)));
+        let mut symbol = section_table::symbol::<Kind>(Layout::Standard);
+        assert!(symbol("  nope").is_err());
+
+        fn hex_only<'a, E>(input: &'a str) -> nom::IResult<&'a str, u32, E>
+        where
+            E: ParseError<&'a str> + FromExternalError<&'a str, ParseIntError>,
+        {
+            hex(8)(input)
+        }
+        assert_eq!(
+            hex_only::<Kind>("8000310"),
+            Err(nom::Err::Error(Kind(ErrorKind::HexDigit)))
+        );
+    }
+
+    #[test]
+    fn test_compatibility() {
+        use crate::{linker_table, map, memory_table, tree};
+
+        assert_eq!(
+            map::line::<Kind>("Memory map:"),
+            Ok(("", Line::MemoryTitle))
+        );
+        assert_eq!(map::hex::<Kind>(8)("80003100"), Ok(("", 0x80003100)));
+        assert!(tree::node::<Kind>(
+            "  1] __start (func,global) found in os.a __start.c"
+        )
+        .is_ok());
+        assert!(crate::section_table::symbol::<Kind>(
+            "  00000000 000004 80003100  4 __start \tos.a __start.c"
+        )
+        .is_ok());
+        assert!(memory_table::debug_entry::<Kind>(
+            "           .debug           000000 00000000"
+        )
+        .is_ok());
+        assert!(memory_table::debug_entry::<Kind>(
+            "            .init  80003100 000024 00000100"
+        )
+        .is_err());
+        assert!(linker_table::entry::<Kind>(
+            "                  _stack_addr 80400000"
+        )
+        .is_ok());
+    }
+}
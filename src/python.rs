@@ -0,0 +1,519 @@
+//! Python bindings, built with the `python` feature, e.g. with
+//! `maturin build`, which builds the `cdylib` that Python imports.
+
+use crate::{
+    demangle::demangle, linker_table, map, map_file, memory_table,
+    section_table, tree,
+};
+use pyo3::{
+    create_exception, exceptions::PyValueError, prelude::*, types::PyList,
+};
+use std::{
+    fmt::{self, Display, Formatter},
+    fs,
+};
+
+create_exception!(cwparse, ParseError, PyValueError);
+
+fn parse_error(input: &str, err: crate::Error) -> PyErr {
+    ParseError::new_err(err.report(input).to_string())
+}
+
+/// A symbol name, e.g. `__start`, `@123` or `...data.0`.
+#[pyclass(module = "cwparse", frozen, skip_from_py_object, eq, hash, str)]
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Identifier(map::Identifier<String>);
+
+impl Display for Identifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[pymethods]
+impl Identifier {
+    #[new]
+    fn new(name: &str) -> PyResult<Self> {
+        name.parse().map(Self).map_err(|err| parse_error(name, err))
+    }
+
+    /// One of `named`, `mangled`, `relative`, `string_base`, `section`,
+    /// `dot_l` or `mapping`.
+    #[getter]
+    fn kind(&self) -> &'static str {
+        self.0.kind()
+    }
+
+    /// The name without any instance or index, e.g. `finfo` for
+    /// `finfo$221`.
+    #[getter]
+    fn name(&self) -> Option<String> {
+        use map::Identifier::*;
+
+        match &self.0 {
+            Named { name, .. } | Mangled { name } | DotL { name } => {
+                Some(name.clone())
+            }
+            Section { name, .. } => Some(name.to_string()),
+            Relative { .. } | StringBase { .. } | Mapping(_) => None,
+        }
+    }
+
+    /// The `$` suffix of a named symbol, e.g. `221` for `finfo$221`.
+    #[getter]
+    fn instance(&self) -> Option<u32> {
+        match self.0 {
+            map::Identifier::Named { instance, .. } => instance,
+            _ => None,
+        }
+    }
+
+    /// The index of a relative, string base or section symbol.
+    #[getter]
+    fn idx(&self) -> Option<u32> {
+        use map::Identifier::*;
+
+        match self.0 {
+            Relative { idx } => Some(idx),
+            StringBase { idx } => Some(idx.into()),
+            Section { idx, .. } => idx.map(Into::into),
+            _ => None,
+        }
+    }
+
+    #[getter]
+    fn demangled(&self) -> Option<String> {
+        demangle(&self.0.to_string())
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Identifier({:?})", self.0.to_string())
+    }
+}
+
+/// A section name, e.g. `.text` or `extabindex`.
+#[pyclass(module = "cwparse", frozen, skip_from_py_object, eq, hash, str)]
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct SectionName(map::SectionName<String>);
+
+impl Display for SectionName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[pymethods]
+impl SectionName {
+    #[new]
+    fn new(name: &str) -> PyResult<Self> {
+        name.parse().map(Self).map_err(|err| parse_error(name, err))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("SectionName({:?})", self.0.to_string())
+    }
+}
+
+/// The object file, and optionally the source file, that a symbol came from.
+#[pyclass(module = "cwparse", frozen, skip_from_py_object, eq, hash, get_all)]
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Origin {
+    obj: String,
+    src: Option<String>,
+    asm: bool,
+}
+
+impl From<&map::Origin<String>> for Origin {
+    fn from(origin: &map::Origin<String>) -> Self {
+        Self {
+            obj: origin.obj.clone(),
+            src: origin.src.clone(),
+            asm: origin.asm,
+        }
+    }
+}
+
+#[pymethods]
+impl Origin {
+    #[new]
+    #[pyo3(signature = (obj, src=None, asm=false))]
+    fn new(obj: String, src: Option<String>, asm: bool) -> Self {
+        Self { obj, src, asm }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Origin({:?}, {:?}, {})", self.obj, self.src, self.asm)
+    }
+}
+
+/// The type, scope and origin of a closure tree node, e.g.
+/// `(func,global) found in os.a __start.c`.
+#[pyclass(module = "cwparse", frozen, skip_from_py_object, eq, hash)]
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Specifier {
+    /// One of `section`, `object`, `func` or `notype`.
+    #[pyo3(get, name = "type")]
+    r#type: &'static str,
+    /// One of `global`, `local` or `weak`.
+    #[pyo3(get)]
+    scope: &'static str,
+    #[pyo3(get)]
+    origin: Origin,
+}
+
+impl From<&tree::Specifier<String>> for Specifier {
+    fn from(specifier: &tree::Specifier<String>) -> Self {
+        Self {
+            r#type: specifier.r#type.as_str(),
+            scope: specifier.scope.as_str(),
+            origin: (&specifier.origin).into(),
+        }
+    }
+}
+
+#[pymethods]
+impl Specifier {
+    fn __repr__(&self) -> String {
+        format!(
+            "Specifier({:?}, {:?}, {})",
+            self.r#type,
+            self.scope,
+            self.origin.__repr__()
+        )
+    }
+}
+
+/// A row of a section layout.
+#[pyclass(module = "cwparse", frozen, skip_from_py_object, get_all)]
+#[derive(Clone)]
+pub struct Symbol {
+    section: SectionName,
+    id: Identifier,
+    addr: u32,
+    virt_addr: u32,
+    file_addr: Option<u32>,
+    /// Only present for symbols that are not entries of another symbol.
+    size: Option<u32>,
+    align: Option<u8>,
+    /// The symbol that an entry symbol is part of.
+    parent: Option<Identifier>,
+    origin: Origin,
+}
+
+impl Symbol {
+    fn new(
+        section: &map::SectionName<String>,
+        symbol: &section_table::Symbol<String>,
+    ) -> Self {
+        let (size, align, parent) = match &symbol.data {
+            section_table::Data::Parent { size, align } => {
+                (Some(*size), Some(*align), None)
+            }
+            section_table::Data::Child { parent } => {
+                (None, None, Some(Identifier(parent.clone())))
+            }
+        };
+
+        Self {
+            section: SectionName(section.clone()),
+            id: Identifier(symbol.id.clone()),
+            addr: symbol.addr,
+            virt_addr: symbol.virt_addr,
+            file_addr: symbol.file_addr,
+            size,
+            align,
+            parent,
+            origin: (&symbol.origin).into(),
+        }
+    }
+}
+
+#[pymethods]
+impl Symbol {
+    fn __repr__(&self) -> String {
+        format!(
+            "Symbol({:?}, {:?}, {:#010x})",
+            self.section.to_string(),
+            self.id.to_string(),
+            self.virt_addr
+        )
+    }
+}
+
+/// A node of a closure tree.
+#[pyclass(module = "cwparse", frozen, skip_from_py_object, get_all)]
+#[derive(Clone)]
+pub struct TreeNode {
+    depth: u32,
+    /// Absent for linker generated symbols and duplicate specifiers.
+    id: Option<Identifier>,
+    /// The name of a linker generated symbol.
+    linker_name: Option<String>,
+    specifier: Option<Specifier>,
+    /// Whether this is a `>>>` line for an unreferenced duplicate.
+    duplicate: bool,
+}
+
+impl From<&tree::Node<String>> for TreeNode {
+    fn from(node: &tree::Node<String>) -> Self {
+        let (id, linker_name, specifier, duplicate) = match &node.data {
+            tree::Data::Linker(name) => {
+                (None, Some(name.clone()), None, false)
+            }
+            tree::Data::Object(id, specifier) => (
+                Some(Identifier(id.clone())),
+                None,
+                Some(specifier.into()),
+                false,
+            ),
+            tree::Data::DuplicateIdentifier(id) => {
+                (Some(Identifier(id.clone())), None, None, true)
+            }
+            tree::Data::DuplicateSpecifier(specifier) => {
+                (None, None, Some(specifier.into()), true)
+            }
+        };
+
+        Self {
+            depth: node.depth,
+            id,
+            linker_name,
+            specifier,
+            duplicate,
+        }
+    }
+}
+
+/// A closure tree, e.g. `Link map of __start`.
+#[pyclass(module = "cwparse", frozen, skip_from_py_object, get_all)]
+#[derive(Clone)]
+pub struct Tree {
+    root: String,
+    nodes: Vec<TreeNode>,
+}
+
+/// A row of the memory map.
+#[pyclass(module = "cwparse", frozen, skip_from_py_object, get_all)]
+#[derive(Clone)]
+pub struct MemoryEntry {
+    name: String,
+    /// Absent for debug sections.
+    virt_addr: Option<u32>,
+    size: u32,
+    file_addr: u32,
+    /// The ROM columns, which are only present in ROM-based builds.
+    rom_addr: Option<u32>,
+    ram_buffer_addr: Option<u32>,
+    s_record_line: Option<u32>,
+    bin_file_addr: Option<u32>,
+    bin_file_name: Option<String>,
+}
+
+impl From<&memory_table::Entry<String>> for MemoryEntry {
+    fn from(entry: &memory_table::Entry<String>) -> Self {
+        let (name, virt_addr) = match &entry.data {
+            memory_table::Data::Main { name, virt_addr } => {
+                (name.to_string(), Some(*virt_addr))
+            }
+            memory_table::Data::Debug { name } => (name.to_string(), None),
+        };
+        let rom = entry.rom.as_ref();
+
+        Self {
+            name,
+            virt_addr,
+            size: entry.size,
+            file_addr: entry.file_addr,
+            rom_addr: rom.map(|rom| rom.rom_addr),
+            ram_buffer_addr: rom.map(|rom| rom.ram_buffer_addr),
+            s_record_line: rom.map(|rom| rom.s_record_line),
+            bin_file_addr: rom.map(|rom| rom.bin_file_addr),
+            bin_file_name: rom.map(|rom| rom.bin_file_name.clone()),
+        }
+    }
+}
+
+/// A row of the linker generated symbols table.
+#[pyclass(module = "cwparse", frozen, skip_from_py_object, get_all)]
+#[derive(Clone)]
+pub struct LinkerEntry {
+    name: String,
+    virt_addr: u32,
+}
+
+impl From<&linker_table::Entry<String>> for LinkerEntry {
+    fn from(entry: &linker_table::Entry<String>) -> Self {
+        Self {
+            name: entry.name.clone(),
+            virt_addr: entry.virt_addr,
+        }
+    }
+}
+
+/// The tables of a parsed map. Iterating over it yields every section layout
+/// symbol.
+#[pyclass(module = "cwparse", frozen, skip_from_py_object)]
+pub struct MapFile(map_file::MapFile<String>);
+
+impl MapFile {
+    fn symbols(&self) -> impl Iterator<Item = Symbol> + '_ {
+        self.0
+            .symbols()
+            .map(|(section, symbol)| Symbol::new(section, symbol))
+    }
+}
+
+#[pymethods]
+impl MapFile {
+    #[staticmethod]
+    fn parse(text: &str) -> PyResult<Self> {
+        crate::parse_map(text)
+            .map(|map_file| Self(map_file.map_str(&mut str::to_owned)))
+            .map_err(|err| parse_error(text, err))
+    }
+
+    #[staticmethod]
+    fn open(path: &str) -> PyResult<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    fn __len__(&self) -> usize {
+        self.0.symbols().count()
+    }
+
+    fn __iter__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Ok(PyList::new(py, self.symbols().collect::<Vec<_>>())?
+            .try_iter()?
+            .into_any())
+    }
+
+    /// Finds the symbol whose data contains `virt_addr`.
+    fn symbol_at(&self, virt_addr: u32) -> Option<Symbol> {
+        let (section, symbol) = self.0.symbol_at(virt_addr)?;
+        Some(Symbol::new(section, symbol))
+    }
+
+    /// Finds the symbols with the given name, which is matched against both
+    /// the mangled and the demangled name.
+    fn find(&self, name: &str) -> Vec<Symbol> {
+        self.symbols()
+            .filter(|symbol| {
+                let id = symbol.id.to_string();
+                id == name || demangle(&id).is_some_and(|d| d == name)
+            })
+            .collect()
+    }
+
+    #[getter]
+    fn trees(&self) -> Vec<Tree> {
+        self.0
+            .trees
+            .iter()
+            .map(|tree| Tree {
+                root: tree.root.clone(),
+                nodes: tree.nodes.iter().map(Into::into).collect(),
+            })
+            .collect()
+    }
+
+    #[getter]
+    fn memory(&self) -> Vec<MemoryEntry> {
+        self.0.memory.iter().map(Into::into).collect()
+    }
+
+    #[getter]
+    fn linker(&self) -> Vec<LinkerEntry> {
+        self.0.linker.iter().map(Into::into).collect()
+    }
+}
+
+/// Parses a whole map.
+#[pyfunction]
+fn parse(text: &str) -> PyResult<MapFile> {
+    MapFile::parse(text)
+}
+
+/// Reads and parses the map at `path`.
+#[pyfunction]
+fn open(path: &str) -> PyResult<MapFile> {
+    MapFile::open(path)
+}
+
+#[pymodule]
+#[pyo3(name = "cwparse")]
+fn python_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("ParseError", m.py().get_type::<ParseError>())?;
+    m.add_class::<Identifier>()?;
+    m.add_class::<SectionName>()?;
+    m.add_class::<Origin>()?;
+    m.add_class::<Specifier>()?;
+    m.add_class::<Symbol>()?;
+    m.add_class::<TreeNode>()?;
+    m.add_class::<Tree>()?;
+    m.add_class::<MemoryEntry>()?;
+    m.add_class::<LinkerEntry>()?;
+    m.add_class::<MapFile>()?;
+    m.add_function(wrap_pyfunction!(parse, m)?)?;
+    m.add_function(wrap_pyfunction!(open, m)?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::python_module;
+    use pyo3::{prelude::*, types::PyDict};
+
+    #[test]
+    fn test_python() {
+        Python::initialize();
+        Python::attach(|py| {
+            let cwparse = PyModule::new(py, "cwparse")?;
+            python_module(&cwparse)?;
+            let locals = PyDict::new(py);
+            locals.set_item("cwparse", cwparse)?;
+            locals.set_item(
+                "text",
+                "\
+Link map of __start\r\n\
+\x20 1] __start (func,global) found in os.a __start.c\r\n\
+\r\n\
+.text section layout\r\n\
+\x20 00000000 000010 80003100  4 __start\x20\tos.a __start.c\r\n\
+\x20 00000010 00002c 80003110  4 __dt__15CMemoryInStreamFv\x20\tCMemoryInStream.o \r\n\
+",
+            )?;
+
+            py.run(
+                c"
+map = cwparse.parse(text)
+assert len(map) == 2
+assert [str(symbol.id) for symbol in map] == [
+    '__start',
+    '__dt__15CMemoryInStreamFv',
+]
+
+symbol = map.symbol_at(0x8000311c)
+assert symbol.id.demangled == 'CMemoryInStream::~CMemoryInStream()'
+assert symbol.section == cwparse.SectionName('.text')
+assert symbol.size == 0x2c
+assert map.find('CMemoryInStream::~CMemoryInStream()')[0].addr == 0x10
+
+specifier = map.trees[0].nodes[0].specifier
+assert specifier.type == 'func'
+assert specifier.origin == cwparse.Origin('os.a', '__start.c')
+assert cwparse.Identifier('finfo$221').instance == 221
+
+try:
+    cwparse.parse('Memory map:\\nnope\\n')
+    assert False
+except cwparse.ParseError as err:
+    assert str(err).startswith('error: ')
+",
+                None,
+                Some(&locals),
+            )
+        })
+        .unwrap();
+    }
+}
@@ -1,41 +1,153 @@
-use crate::map::{
-    hex, identifier, origin, padded, section_name, Identifier, Line, Origin,
-    SectionName,
+use crate::{
+    map::{
+        c_name, hex_digits, hex_min, identifier, origin, section_name,
+        Identifier, Line, Origin, SectionName,
+    },
+    span::{FieldKind, Fields},
 };
 use nom::{
     branch::alt,
     bytes::complete::tag,
-    character::complete::{char, digit1},
-    combinator::{map, map_res},
-    error::{FromExternalError, ParseError},
+    character::complete::{char, digit1, space1},
+    combinator::{consumed, map, map_res, opt},
+    error::{context, ContextError, FromExternalError, ParseError},
     multi::count,
-    sequence::{delimited, pair, terminated, tuple},
-    IResult, Parser,
+    sequence::{delimited, pair, preceded, terminated, tuple},
+    IResult,
 };
-use std::num::ParseIntError;
+use std::{
+    fmt::{self, Display, Formatter},
+    num::ParseIntError,
+};
+
+/// The columns of a section layout, as announced by its column header.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Layout {
+    /// Starting address, size, virtual address and alignment.
+    #[default]
+    Standard,
+    /// Like [`Layout::Standard`], with a file offset after the virtual
+    /// address, as in the maps of ARM overlays.
+    FileOffset,
+}
 
-#[derive(Debug, Eq, PartialEq, Hash)]
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(serialize = "S: serde::Serialize + std::fmt::Display"))
+)]
 pub enum Data<S: Eq + PartialEq> {
     Parent { size: u32, align: u8 },
     Child { parent: Identifier<S> },
 }
 
-#[derive(Debug, Eq, PartialEq, Hash)]
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(serialize = "S: serde::Serialize + std::fmt::Display"))
+)]
 pub struct Symbol<S: Eq + PartialEq> {
     pub addr: u32,
     pub virt_addr: u32,
+    /// Only present in [`Layout::FileOffset`] tables.
+    pub file_addr: Option<u32>,
     pub data: Data<S>,
     pub id: Identifier<S>,
     pub origin: Origin<S>,
+    /// The number of spaces before the alignment of a parent, which is
+    /// right-aligned, or before the name of a child.
+    pub padding: usize,
+}
+
+impl<S: Eq + PartialEq> Symbol<S> {
+    pub fn map_str<T, F>(self, f: &mut F) -> Symbol<T>
+    where
+        T: Eq + PartialEq,
+        F: FnMut(S) -> T,
+    {
+        Symbol {
+            addr: self.addr,
+            virt_addr: self.virt_addr,
+            file_addr: self.file_addr,
+            data: match self.data {
+                Data::Parent { size, align } => Data::Parent { size, align },
+                Data::Child { parent } => Data::Child {
+                    parent: parent.map_str(f),
+                },
+            },
+            id: self.id.map_str(f),
+            origin: self.origin.map_str(f),
+            padding: self.padding,
+        }
+    }
+}
+
+impl<S: Eq + PartialEq> Symbol<S> {
+    /// Whether the data of the symbol contains `virt_addr`.
+    ///
+    /// Entry symbols, which have no size of their own, only contain their
+    /// exact address.
+    pub fn contains(&self, virt_addr: u32) -> bool {
+        let size = match self.data {
+            Data::Parent { size, .. } => size.max(1),
+            Data::Child { .. } => 1,
+        };
+        (self.virt_addr..self.virt_addr.saturating_add(size))
+            .contains(&virt_addr)
+    }
 }
 
+impl<S: Eq + PartialEq + Display> Display for Symbol<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "  {:08x} ", self.addr)?;
+        match self.data {
+            Data::Parent { size, .. } => write!(f, "{size:06x} ")?,
+            Data::Child { .. } => f.write_str("000000 ")?,
+        }
+        write!(f, "{:08x}", self.virt_addr)?;
+        if let Some(file_addr) = self.file_addr {
+            write!(f, " {file_addr:08x}")?;
+        }
+        write!(f, "{:1$}", "", self.padding)?;
+        match &self.data {
+            Data::Parent { align, .. } => write!(f, "{align} {}", self.id)?,
+            Data::Child { parent } => {
+                write!(f, "{} (entry of {parent})", self.id)?
+            }
+        }
+        write!(f, " \t{}", self.origin)
+    }
+}
+
+/// Kept for compatibility, see [`parsers`](crate::parsers).
 pub fn title<'a, E>(
     input: &'a str,
 ) -> IResult<&'a str, SectionName<&'a str>, E>
 where
     E: ParseError<&'a str>,
 {
-    terminated(section_name, tag(" section layout"))(input)
+    crate::parsers::section_table::title(input)
+}
+
+/// Like [`title`], but also locates the section name.
+pub(crate) fn title_located<'a, E>(
+    input: &'a str,
+) -> IResult<&'a str, (SectionName<&'a str>, Fields<'a>), E>
+where
+    E: ParseError<&'a str>,
+{
+    map(
+        terminated(
+            consumed(alt((section_name, map(c_name, SectionName::Overlay)))),
+            tag(" section layout"),
+        ),
+        |(text, name)| {
+            (name, Fields::new().with(FieldKind::SectionName, text))
+        },
+    )(input)
 }
 
 pub fn columns0<'a, E>(input: &'a str) -> IResult<&'a str, Line<&'a str>, E>
@@ -48,15 +160,37 @@ where
             tag("Starting"),
             count(char(' '), 8),
             tag("Virtual"),
+            opt(pair(count(char(' '), 2), tag("File"))),
         )),
-        |_| Line::SectionColumns0,
+        |(_, _, _, _, file)| {
+            Line::SectionColumns0(match file {
+                Some(_) => Layout::FileOffset,
+                None => Layout::Standard,
+            })
+        },
     )(input)
 }
 
+/// Parses the second line of the column header of the standard layout.
+///
+/// Kept for compatibility, see [`parsers`](crate::parsers).
 pub fn columns1<'a, E>(input: &'a str) -> IResult<&'a str, Line<&'a str>, E>
 where
     E: ParseError<&'a str>,
 {
+    columns1_for(Layout::Standard)(input)
+}
+
+pub(crate) fn columns1_for<'a, E>(
+    layout: Layout,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Line<&'a str>, E>
+where
+    E: ParseError<&'a str>,
+{
+    let offset = match layout {
+        Layout::Standard => "",
+        Layout::FileOffset => "  offset",
+    };
     map(
         tuple((
             count(char(' '), 2),
@@ -65,108 +199,242 @@ where
             tag("Size"),
             count(char(' '), 3),
             tag("address"),
+            tag(offset),
         )),
         |_| Line::SectionColumns1,
-    )(input)
+    )
 }
 
+/// Parses the separator below the column header of the standard layout.
+///
+/// Kept for compatibility, see [`parsers`](crate::parsers).
 pub fn separator<'a, E>(input: &'a str) -> IResult<&'a str, Line<&'a str>, E>
 where
     E: ParseError<&'a str>,
 {
-    map(pair(count(char(' '), 2), count(char('-'), 23)), |_| {
+    separator_for(Layout::Standard)(input)
+}
+
+pub(crate) fn separator_for<'a, E>(
+    layout: Layout,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Line<&'a str>, E>
+where
+    E: ParseError<&'a str>,
+{
+    let len = match layout {
+        Layout::Standard => 23,
+        Layout::FileOffset => 33,
+    };
+    map(pair(count(char(' '), 2), count(char('-'), len)), |_| {
         Line::SectionSeparator
-    })(input)
+    })
 }
 
+/// Parses a row of a table with the standard layout.
+///
+/// Kept for compatibility, see [`parsers`](crate::parsers).
 pub fn symbol<'a, E>(input: &'a str) -> IResult<&'a str, Symbol<&'a str>, E>
 where
     E: ParseError<&'a str>
         + FromExternalError<&'a str, ParseIntError>
         + FromExternalError<&'a str, &'static str>,
 {
-    map(
-        tuple((
-            delimited(count(char(' '), 2), hex(8), char(' ')),
-            terminated(alt((parent, child)), tag("\x20\t")),
-            origin,
-        )),
-        |(addr, (virt_addr, data, id), origin)| Symbol {
-            addr,
-            virt_addr,
-            data,
-            id,
-            origin,
-        },
-    )(input)
+    crate::parsers::section_table::symbol(Layout::Standard)(input)
 }
 
+/// Parses a row of a table with the given layout, which decides whether the
+/// file offset column is present, so that a symbol whose name happens to be
+/// eight hex digits is never taken for one.
+pub(crate) fn symbol_for<'a, E>(
+    layout: Layout,
+) -> impl FnMut(&'a str) -> IResult<&'a str, (Symbol<&'a str>, Fields<'a>), E>
+where
+    E: ParseError<&'a str>
+        + FromExternalError<&'a str, ParseIntError>
+        + FromExternalError<&'a str, &'static str>
+        + ContextError<&'a str>,
+{
+    context(
+        "section symbol",
+        map(
+            tuple((
+                delimited(
+                    space1,
+                    context("starting address", consumed(hex_digits(8))),
+                    space1,
+                ),
+                terminated(
+                    alt((
+                        move |input| parent(layout, input),
+                        move |input| child(layout, input),
+                    )),
+                    tag("\x20\t"),
+                ),
+                context("object file", origin),
+            )),
+            |(
+                (addr_text, addr),
+                (virt_addr, file_addr, padding, data, id, fields),
+                origin,
+            )| {
+                let fields = Fields::new()
+                    .with(FieldKind::Addr, addr_text)
+                    .chain(fields)
+                    .with_origin(&origin);
+                let symbol = Symbol {
+                    addr,
+                    virt_addr,
+                    file_addr,
+                    data,
+                    id,
+                    origin,
+                    padding,
+                };
+                (symbol, fields)
+            },
+        ),
+    )
+}
+
+#[allow(clippy::from_str_radix_10)]
 fn align<'a, E>(input: &'a str) -> IResult<&'a str, u8, E>
 where
     E: ParseError<&'a str> + FromExternalError<&'a str, ParseIntError>,
-    E: ParseError<&'a str> + FromExternalError<&'a str, &'static str>,
 {
-    map_res(padded(2).and_then(digit1), |n| u8::from_str_radix(n, 10))(input)
+    map_res(digit1, |n| u8::from_str_radix(n, 10))(input)
 }
 
-fn child<'a, E>(
+type Row<'a> = (
+    u32,
+    Option<u32>,
+    usize,
+    Data<&'a str>,
+    Identifier<&'a str>,
+    Fields<'a>,
+);
+
+fn file_addr<'a, E>(
+    layout: Layout,
     input: &'a str,
-) -> IResult<&'a str, (u32, Data<&'a str>, Identifier<&'a str>), E>
+) -> IResult<&'a str, Option<(&'a str, u32)>, E>
 where
-    E: ParseError<&'a str> + FromExternalError<&'a str, ParseIntError>,
+    E: ParseError<&'a str>
+        + FromExternalError<&'a str, ParseIntError>
+        + FromExternalError<&'a str, &'static str>
+        + ContextError<&'a str>,
+{
+    match layout {
+        Layout::Standard => Ok((input, None)),
+        Layout::FileOffset => map(
+            preceded(space1, context("file offset", consumed(hex_digits(8)))),
+            Some,
+        )(input),
+    }
+}
+
+fn child<'a, E>(layout: Layout, input: &'a str) -> IResult<&'a str, Row<'a>, E>
+where
+    E: ParseError<&'a str>
+        + FromExternalError<&'a str, ParseIntError>
+        + FromExternalError<&'a str, &'static str>
+        + ContextError<&'a str>,
 {
     map(
         tuple((
-            terminated(count(char('0'), 6), char(' ')),
-            terminated(hex(8), char(' ')),
-            terminated(identifier, char(' ')),
-            parent_identifier,
+            terminated(count(char('0'), 6), space1),
+            context("virtual address", consumed(hex_digits(8))),
+            |input| file_addr(layout, input),
+            map(space1, str::len),
+            terminated(context("symbol", consumed(identifier)), space1),
+            context("parent symbol", parent_identifier),
         )),
-        |(_, virt_addr, id, parent)| (virt_addr, Data::Child { parent }, id),
+        |(
+            _,
+            (virt_text, virt_addr),
+            file_addr,
+            padding,
+            (id_text, id),
+            (parent_text, parent),
+        )| {
+            let fields = Fields::new()
+                .with(FieldKind::VirtAddr, virt_text)
+                .with_opt(FieldKind::FileAddr, file_addr.map(|(text, _)| text))
+                .with(FieldKind::Identifier, id_text)
+                .with(FieldKind::Parent, parent_text);
+            (
+                virt_addr,
+                file_addr.map(|(_, file_addr)| file_addr),
+                padding,
+                Data::Child { parent },
+                id,
+                fields,
+            )
+        },
     )(input)
 }
 
+/// Parses e.g. `(entry of memset)`, along with the text of the identifier.
 fn parent_identifier<'a, E>(
     input: &'a str,
-) -> IResult<&'a str, Identifier<&'a str>, E>
+) -> IResult<&'a str, (&'a str, Identifier<&'a str>), E>
 where
     E: ParseError<&'a str> + FromExternalError<&'a str, ParseIntError>,
 {
-    delimited(tag("(entry of "), identifier, char(')'))(input)
+    delimited(tag("(entry of "), consumed(identifier), char(')'))(input)
 }
 
 fn parent<'a, E>(
+    layout: Layout,
     input: &'a str,
-) -> IResult<&'a str, (u32, Data<&'a str>, Identifier<&'a str>), E>
+) -> IResult<&'a str, Row<'a>, E>
 where
-    E: ParseError<&'a str> + FromExternalError<&'a str, ParseIntError>,
-    E: ParseError<&'a str> + FromExternalError<&'a str, &'static str>,
+    E: ParseError<&'a str>
+        + FromExternalError<&'a str, ParseIntError>
+        + FromExternalError<&'a str, &'static str>
+        + ContextError<&'a str>,
 {
     map(
         tuple((
-            terminated(hex(6), char(' ')),
-            terminated(hex(8), char(' ')),
-            terminated(align, char(' ')),
-            identifier,
+            terminated(context("size", consumed(hex_min(6))), space1),
+            context("virtual address", consumed(hex_digits(8))),
+            |input| file_addr(layout, input),
+            map(space1, str::len),
+            terminated(context("alignment", consumed(align)), space1),
+            context("symbol", consumed(identifier)),
         )),
-        |(size, virt_addr, align, id)| {
-            (virt_addr, Data::Parent { size, align }, id)
+        |(
+            (size_text, size),
+            (virt_text, virt_addr),
+            file_addr,
+            padding,
+            (align_text, align),
+            (id_text, id),
+        )| {
+            let fields = Fields::new()
+                .with(FieldKind::Size, size_text)
+                .with(FieldKind::VirtAddr, virt_text)
+                .with_opt(FieldKind::FileAddr, file_addr.map(|(text, _)| text))
+                .with(FieldKind::Align, align_text)
+                .with(FieldKind::Identifier, id_text);
+            (
+                virt_addr,
+                file_addr.map(|(_, file_addr)| file_addr),
+                padding,
+                Data::Parent { size, align },
+                id,
+                fields,
+            )
         },
     )(input)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{columns0, columns1, separator, symbol, title, Data, Symbol};
+    use super::{Data, Layout, Symbol};
     use crate::{
-        map::{Identifier, Origin, SectionName},
+        map::{Identifier, LineParser, MappingSymbol, Origin, SectionName},
         utils::test_utils::assert_diff,
     };
-    use nom::{
-        branch::alt,
-        combinator::{all_consuming, map},
-    };
-    use nom_supreme::error::ErrorTree;
 
     #[test]
     fn test_section_table() {
@@ -180,13 +448,20 @@ mod tests {
 \x20 00000000 0001cc 80003100  1 .init\x20\t__start.o \r\n\
 \x20 00000000 0000f0 80003100  4 __start\x20\t__start.o \r\n\
 \x20 00000250 000000 80003350 __fill_mem (entry of memset) \t__mem.o \r\n\
+\x20 00000260 000000 80003360 deadbeef (entry of memset) \t__mem.o \r\n\
+main_overlay_1 section layout\r\n\
+\x20 Starting        Virtual  File\r\n\
+\x20 address  Size   address  offset\r\n\
+\x20 ---------------------------------\r\n\
+\x20 00000000 000088 021c0000 00088c00  4 $a\x20\tovl1.o \r\n\
+\x20 00000010 000000 021c0010 00088c10 deadbeef (entry of $a) \tovl1.o \r\n\
 "
         .split_terminator("\r\n")
         .collect::<Vec<_>>();
 
         let expected: Vec<Line<&str>> = vec![
             Line::SectionTitle(SectionName::Init),
-            Line::SectionColumns0,
+            Line::SectionColumns0(Layout::Standard),
             Line::SectionColumns1,
             Line::SectionSeparator,
             Line::SectionSymbol(Symbol {
@@ -196,6 +471,7 @@ mod tests {
                     align: 1,
                 },
                 virt_addr: 0x80003100,
+                file_addr: None,
                 id: Identifier::Section {
                     name: SectionName::Init,
                     idx: None,
@@ -205,6 +481,7 @@ mod tests {
                     src: None,
                     asm: false,
                 },
+                padding: 2,
             }),
             Line::SectionSymbol(Symbol {
                 addr: 0,
@@ -213,6 +490,7 @@ mod tests {
                     align: 4,
                 },
                 virt_addr: 0x80003100,
+                file_addr: None,
                 id: Identifier::Named {
                     name: "__start",
                     instance: None,
@@ -222,6 +500,7 @@ mod tests {
                     src: None,
                     asm: false,
                 },
+                padding: 2,
             }),
             Line::SectionSymbol(Symbol {
                 addr: 0x250,
@@ -232,6 +511,7 @@ mod tests {
                     },
                 },
                 virt_addr: 0x80003350,
+                file_addr: None,
                 id: Identifier::Named {
                     name: "__fill_mem",
                     instance: None,
@@ -241,24 +521,78 @@ mod tests {
                     src: None,
                     asm: false,
                 },
+                padding: 1,
+            }),
+            Line::SectionSymbol(Symbol {
+                addr: 0x260,
+                data: Data::Child {
+                    parent: Identifier::Named {
+                        name: "memset",
+                        instance: None,
+                    },
+                },
+                virt_addr: 0x80003360,
+                file_addr: None,
+                id: Identifier::Named {
+                    name: "deadbeef",
+                    instance: None,
+                },
+                origin: Origin {
+                    obj: "__mem.o",
+                    src: None,
+                    asm: false,
+                },
+                padding: 1,
+            }),
+            Line::SectionTitle(SectionName::Overlay("main_overlay_1")),
+            Line::SectionColumns0(Layout::FileOffset),
+            Line::SectionColumns1,
+            Line::SectionSeparator,
+            Line::SectionSymbol(Symbol {
+                addr: 0,
+                data: Data::Parent {
+                    size: 0x88,
+                    align: 4,
+                },
+                virt_addr: 0x021c0000,
+                file_addr: Some(0x88c00),
+                id: Identifier::Mapping(MappingSymbol::Arm),
+                origin: Origin {
+                    obj: "ovl1.o",
+                    src: None,
+                    asm: false,
+                },
+                padding: 2,
+            }),
+            Line::SectionSymbol(Symbol {
+                addr: 0x10,
+                data: Data::Child {
+                    parent: Identifier::Mapping(MappingSymbol::Arm),
+                },
+                virt_addr: 0x021c0010,
+                file_addr: Some(0x88c10),
+                id: Identifier::Named {
+                    name: "deadbeef",
+                    instance: None,
+                },
+                origin: Origin {
+                    obj: "ovl1.o",
+                    src: None,
+                    asm: false,
+                },
+                padding: 1,
             }),
         ];
 
-        let mut parser = alt::<_, _, ErrorTree<&str>, _>((
-            all_consuming(map(title, Line::SectionTitle)),
-            all_consuming(map(columns0, |_| Line::SectionColumns0)),
-            all_consuming(map(columns1, |_| Line::SectionColumns1)),
-            all_consuming(map(separator, |_| Line::SectionSeparator)),
-            all_consuming(map(symbol, Line::SectionSymbol)),
-        ));
+        let mut parser = LineParser::new();
 
         let (input_len, expected_len) = (&input.len(), &expected.len());
 
         // TODO: Factor out test boilerplate
         for (input, expected) in input.into_iter().zip(expected) {
-            let actual = parser(input);
+            let actual = parser.parse(input);
             match actual {
-                Ok((_, actual)) => assert_diff(&expected, &actual),
+                Ok(actual) => assert_diff(&expected, &actual),
                 Err(err) => panic!("{err:#?}"),
             }
         }
@@ -0,0 +1,289 @@
+use crate::{
+    error::{offset, Error},
+    map::{Line, LineParser, Origin},
+};
+use nom_supreme::error::ErrorTree;
+use std::ops::Range;
+
+/// A byte range within a map.
+#[derive(
+    Debug, Default, Eq, PartialEq, Ord, PartialOrd, Clone, Copy, Hash,
+)]
+pub struct Span {
+    pub offset: usize,
+    pub len: usize,
+}
+
+impl Span {
+    /// The span of `part`, which must be a slice of `input`.
+    pub fn locate(input: &str, part: &str) -> Self {
+        Self {
+            offset: offset(input, part),
+            len: part.len(),
+        }
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.len
+    }
+
+    pub fn contains(&self, offset: usize) -> bool {
+        self.range().contains(&offset)
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+pub enum FieldKind {
+    /// The root of a closure tree title.
+    Root,
+    /// The name of a section layout title or memory map entry.
+    SectionName,
+    Depth,
+    Identifier,
+    /// The identifier in `(entry of ...)`.
+    Parent,
+    Type,
+    Scope,
+    Object,
+    Source,
+    Addr,
+    Size,
+    VirtAddr,
+    FileAddr,
+    Align,
+    /// The name of a linker generated symbol.
+    Name,
+    /// The symbol that an island branches to.
+    Target,
+    RomAddr,
+    RamBufferAddr,
+    SRecordLine,
+    BinFileAddr,
+    BinFileName,
+}
+
+/// The location of a single field of a parsed line.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+pub struct Field {
+    pub kind: FieldKind,
+    pub span: Span,
+}
+
+/// A parsed line along with the location of the line and of its fields.
+#[derive(Debug, Eq, PartialEq, Hash)]
+pub struct LocatedLine<'a> {
+    /// The line number, starting at 0.
+    pub number: usize,
+    /// The span of the line, without its line ending.
+    pub span: Span,
+    pub line: Line<&'a str>,
+    pub fields: Vec<Field>,
+}
+
+/// The fields of a line as located by its parser, as slices of the line.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Fields<'a> {
+    fields: Vec<(FieldKind, &'a str)>,
+}
+
+impl<'a> Fields<'a> {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn with(mut self, kind: FieldKind, part: &'a str) -> Self {
+        self.fields.push((kind, part));
+        self
+    }
+
+    pub(crate) fn with_opt(
+        self,
+        kind: FieldKind,
+        part: Option<&'a str>,
+    ) -> Self {
+        match part {
+            Some(part) => self.with(kind, part),
+            None => self,
+        }
+    }
+
+    /// Adds the object file and source file of `origin`.
+    pub(crate) fn with_origin(self, origin: &Origin<&'a str>) -> Self {
+        self.with(FieldKind::Object, origin.obj)
+            .with_opt(FieldKind::Source, origin.src)
+    }
+
+    pub(crate) fn chain(mut self, other: Fields<'a>) -> Self {
+        self.fields.extend(other.fields);
+        self
+    }
+
+    pub(crate) fn iter(
+        &self,
+    ) -> impl Iterator<Item = (FieldKind, &'a str)> + '_ {
+        self.fields.iter().copied()
+    }
+
+    /// The location of each field within `input`, which the line is a slice
+    /// of.
+    pub(crate) fn locate(&self, input: &str) -> Vec<Field> {
+        self.iter()
+            .map(|(kind, part)| Field {
+                kind,
+                span: Span::locate(input, part),
+            })
+            .collect()
+    }
+}
+
+/// Parses every line of a map, recording the location of each field.
+pub fn located_lines(input: &str) -> Result<Vec<LocatedLine<'_>>, Error> {
+    let mut parser = LineParser::new();
+
+    input
+        .lines()
+        .enumerate()
+        .map(|(number, text)| {
+            let (_, (line, fields)) = parser
+                .line_located::<ErrorTree<&str>>(text)
+                .map_err(|err| {
+                    Error::from_nom(text, err)
+                        .relocate(input, Span::locate(input, text).offset)
+                })?;
+            Ok(LocatedLine {
+                number,
+                span: Span::locate(input, text),
+                fields: fields.locate(input),
+                line,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{located_lines, FieldKind, Span};
+
+    #[test]
+    fn test_spans() {
+        let input = "\
+Link map of __start\r\n\
+\x20 1] __start (func,weak) found in os.a __start.c\r\n\
+.init section layout\r\n\
+\x20 Starting        Virtual  File\r\n\
+\x20 00000000 0000f0 80003100 00000100  4 __start\x20\t__start.o \r\n\
+\x20 00000250 000000 80003350 00000350 __fill_mem (entry of memset) \t__mem.o \r\n\
+Memory map:\r\n\
+\x20           .init  80003100 000023a8 000001c0\r\n\
+.text section layout\r\n\
+\x20 00000250 000000 80003350 memset (entry of memset) \tmy lib.a mem file.c\r\n\
+";
+
+        let lines = located_lines(input).unwrap();
+        let fields = |number: usize| {
+            lines[number]
+                .fields
+                .iter()
+                .map(|field| (field.kind, &input[field.span.range()]))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            lines[1].span,
+            Span {
+                offset: 21,
+                len: 48
+            }
+        );
+        assert_eq!(
+            fields(1),
+            vec![
+                (FieldKind::Depth, "1"),
+                (FieldKind::Identifier, "__start"),
+                (FieldKind::Type, "func"),
+                (FieldKind::Scope, "weak"),
+                (FieldKind::Object, "os.a"),
+                (FieldKind::Source, "__start.c"),
+            ]
+        );
+        assert_eq!(
+            fields(4),
+            vec![
+                (FieldKind::Addr, "00000000"),
+                (FieldKind::Size, "0000f0"),
+                (FieldKind::VirtAddr, "80003100"),
+                (FieldKind::FileAddr, "00000100"),
+                (FieldKind::Align, "4"),
+                (FieldKind::Identifier, "__start"),
+                (FieldKind::Object, "__start.o"),
+            ]
+        );
+        assert_eq!(
+            fields(5),
+            vec![
+                (FieldKind::Addr, "00000250"),
+                (FieldKind::VirtAddr, "80003350"),
+                (FieldKind::FileAddr, "00000350"),
+                (FieldKind::Identifier, "__fill_mem"),
+                (FieldKind::Parent, "memset"),
+                (FieldKind::Object, "__mem.o"),
+            ]
+        );
+        assert_eq!(
+            fields(7),
+            vec![
+                (FieldKind::SectionName, ".init"),
+                (FieldKind::VirtAddr, "80003100"),
+                (FieldKind::Size, "000023a8"),
+                (FieldKind::FileAddr, "000001c0"),
+            ]
+        );
+        // Without a file offset column, and with spaces in the file names.
+        assert_eq!(
+            fields(9),
+            vec![
+                (FieldKind::Addr, "00000250"),
+                (FieldKind::VirtAddr, "80003350"),
+                (FieldKind::Identifier, "memset"),
+                (FieldKind::Parent, "memset"),
+                (FieldKind::Object, "my lib.a"),
+                (FieldKind::Source, "mem file.c"),
+            ]
+        );
+        assert_eq!(
+            lines[9].fields[3].span.offset,
+            lines[9].fields[2].span.offset + "memset (entry of ".len()
+        );
+    }
+
+    #[test]
+    fn test_rom_spans() {
+        let input = "\
+Memory map:\r\n\
+\x20                  Starting Size     File     ROM      RAM Buffer  S-Record Bin File Bin File\r\n\
+\x20                  address           Offset   Address  Address     Line     Offset   Name\r\n\
+\x20           .init  ffe00000 00000318 00000200 fff00000 ffe00000           2 00000000 rom.bin\r\n\
+";
+
+        let lines = located_lines(input).unwrap();
+        let fields = lines[3]
+            .fields
+            .iter()
+            .map(|field| (field.kind, &input[field.span.range()]))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            vec![
+                (FieldKind::SectionName, ".init"),
+                (FieldKind::VirtAddr, "ffe00000"),
+                (FieldKind::Size, "00000318"),
+                (FieldKind::FileAddr, "00000200"),
+                (FieldKind::RomAddr, "fff00000"),
+                (FieldKind::RamBufferAddr, "ffe00000"),
+                (FieldKind::SRecordLine, "2"),
+                (FieldKind::BinFileAddr, "00000000"),
+                (FieldKind::BinFileName, "rom.bin"),
+            ]
+        );
+    }
+}
@@ -1,16 +1,23 @@
-use crate::map::{c_name, identifier, origin, Identifier, Origin};
+use crate::{
+    map::{c_name, identifier, origin, Identifier, Origin},
+    span::{FieldKind, Fields},
+};
 use nom::{
     branch::alt,
     bytes::complete::{tag, take_while},
     character::complete::{char, digit1},
-    combinator::{map, map_res},
-    error::{FromExternalError, ParseError},
+    combinator::{consumed, map, map_res},
+    error::{context, ContextError, FromExternalError, ParseError},
     sequence::{delimited, pair, preceded, separated_pair, terminated, tuple},
     IResult,
 };
-use std::num::ParseIntError;
+use std::{
+    fmt::{self, Display, Formatter},
+    num::ParseIntError,
+};
 
 #[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Type {
     None,
     Section,
@@ -19,13 +26,19 @@ pub enum Type {
 }
 
 #[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Scope {
     Global,
     Local,
     Weak,
 }
 
-#[derive(Debug, Eq, PartialEq, Hash)]
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(serialize = "S: serde::Serialize + std::fmt::Display"))
+)]
 pub enum Data<S: Eq + PartialEq> {
     Linker(S),
     Object(Identifier<S>, Specifier<S>),
@@ -33,19 +46,136 @@ pub enum Data<S: Eq + PartialEq> {
     DuplicateSpecifier(Specifier<S>),
 }
 
-#[derive(Debug, Eq, PartialEq, Hash)]
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(serialize = "S: serde::Serialize + std::fmt::Display"))
+)]
 pub struct Node<S: Eq + PartialEq> {
     pub depth: u32,
     pub data: Data<S>,
 }
 
-#[derive(Debug, Eq, PartialEq, Hash)]
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Specifier<S: Eq + PartialEq> {
     pub r#type: Type,
     pub scope: Scope,
     pub origin: Origin<S>,
 }
 
+impl Type {
+    /// The spelling used in closure trees, e.g. `func`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Type::None => "notype",
+            Type::Section => "section",
+            Type::Object => "object",
+            Type::Function => "func",
+        }
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Scope {
+    /// The spelling used in closure trees, e.g. `global`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::Global => "global",
+            Scope::Local => "local",
+            Scope::Weak => "weak",
+        }
+    }
+}
+
+impl Display for Scope {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl<S: Eq + PartialEq> Node<S> {
+    pub fn map_str<T, F>(self, f: &mut F) -> Node<T>
+    where
+        T: Eq + PartialEq,
+        F: FnMut(S) -> T,
+    {
+        Node {
+            depth: self.depth,
+            data: self.data.map_str(f),
+        }
+    }
+}
+
+impl<S: Eq + PartialEq> Data<S> {
+    pub fn map_str<T, F>(self, f: &mut F) -> Data<T>
+    where
+        T: Eq + PartialEq,
+        F: FnMut(S) -> T,
+    {
+        match self {
+            Data::Linker(name) => Data::Linker(f(name)),
+            Data::Object(id, specifier) => {
+                Data::Object(id.map_str(f), specifier.map_str(f))
+            }
+            Data::DuplicateIdentifier(id) => {
+                Data::DuplicateIdentifier(id.map_str(f))
+            }
+            Data::DuplicateSpecifier(specifier) => {
+                Data::DuplicateSpecifier(specifier.map_str(f))
+            }
+        }
+    }
+}
+
+impl<S: Eq + PartialEq> Specifier<S> {
+    pub fn map_str<T, F>(self, f: &mut F) -> Specifier<T>
+    where
+        T: Eq + PartialEq,
+        F: FnMut(S) -> T,
+    {
+        Specifier {
+            r#type: self.r#type,
+            scope: self.scope,
+            origin: self.origin.map_str(f),
+        }
+    }
+}
+
+impl<S: Eq + PartialEq + Display> Display for Node<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:1$}{2}] ", "", self.depth as usize + 1, self.depth)?;
+        match &self.data {
+            Data::Linker(name) => {
+                write!(f, "{name} found as linker generated symbol")
+            }
+            Data::Object(id, specifier) => write!(f, "{id} {specifier}"),
+            Data::DuplicateIdentifier(id) => {
+                write!(f, ">>> UNREFERENCED DUPLICATE {id}")
+            }
+            Data::DuplicateSpecifier(specifier) => {
+                write!(f, ">>> {specifier}")
+            }
+        }
+    }
+}
+
+impl<S: Eq + PartialEq + Display> Display for Specifier<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "({},{}) found in {}",
+            self.r#type, self.scope, self.origin
+        )
+    }
+}
+
 pub fn title<'a, E>(input: &'a str) -> IResult<&'a str, &'a str, E>
 where
     E: ParseError<&'a str>,
@@ -53,13 +183,37 @@ where
     preceded(tag("Link map of "), c_name)(input)
 }
 
+/// Kept for compatibility, see [`parsers`](crate::parsers).
 pub fn node<'a, E>(input: &'a str) -> IResult<&'a str, Node<&'a str>, E>
 where
     E: ParseError<&'a str> + FromExternalError<&'a str, ParseIntError>,
 {
-    map(
-        pair(depth, alt((linker_data, object_data, duplicate))),
-        |(depth, data)| Node { depth, data },
+    crate::parsers::tree::node(input)
+}
+
+/// Like [`node`], but also locates its fields.
+pub(crate) fn node_located<'a, E>(
+    input: &'a str,
+) -> IResult<&'a str, (Node<&'a str>, Fields<'a>), E>
+where
+    E: ParseError<&'a str>
+        + FromExternalError<&'a str, ParseIntError>
+        + ContextError<&'a str>,
+{
+    context(
+        "tree node",
+        map(
+            pair(
+                context("depth", depth),
+                alt((linker_data, object_data, duplicate)),
+            ),
+            |((text, depth), (data, fields))| {
+                (
+                    Node { depth, data },
+                    Fields::new().with(FieldKind::Depth, text).chain(fields),
+                )
+            },
+        ),
     )(input)
 }
 
@@ -90,79 +244,129 @@ where
     ))(input)
 }
 
-fn linker_data<'a, E>(input: &'a str) -> IResult<&'a str, Data<&'a str>, E>
+type Located<'a, T> = (T, Fields<'a>);
+
+fn linker_data<'a, E>(
+    input: &'a str,
+) -> IResult<&'a str, Located<'a, Data<&'a str>>, E>
 where
     E: ParseError<&'a str>,
 {
     map(
         terminated(c_name, tag(" found as linker generated symbol")),
-        Data::Linker,
+        |name| {
+            (
+                Data::Linker(name),
+                Fields::new().with(FieldKind::Identifier, name),
+            )
+        },
     )(input)
 }
 
-fn object_data<'a, E>(input: &'a str) -> IResult<&'a str, Data<&'a str>, E>
+fn object_data<'a, E>(
+    input: &'a str,
+) -> IResult<&'a str, Located<'a, Data<&'a str>>, E>
 where
-    E: ParseError<&'a str> + FromExternalError<&'a str, ParseIntError>,
+    E: ParseError<&'a str>
+        + FromExternalError<&'a str, ParseIntError>
+        + ContextError<&'a str>,
 {
     map(
-        tuple((terminated(identifier, char(' ')), specifier)),
-        |(id, loc)| Data::Object(id, loc),
+        tuple((
+            terminated(context("symbol", consumed(identifier)), char(' ')),
+            specifier,
+        )),
+        |((text, id), (loc, fields))| {
+            (
+                Data::Object(id, loc),
+                Fields::new()
+                    .with(FieldKind::Identifier, text)
+                    .chain(fields),
+            )
+        },
     )(input)
 }
 
-fn duplicate<'a, E>(input: &'a str) -> IResult<&'a str, Data<&'a str>, E>
+fn duplicate<'a, E>(
+    input: &'a str,
+) -> IResult<&'a str, Located<'a, Data<&'a str>>, E>
 where
-    E: ParseError<&'a str> + FromExternalError<&'a str, ParseIntError>,
+    E: ParseError<&'a str>
+        + FromExternalError<&'a str, ParseIntError>
+        + ContextError<&'a str>,
 {
     preceded(
         tag(">>> "),
         alt((
             map(
-                preceded(tag("UNREFERENCED DUPLICATE "), identifier),
-                Data::DuplicateIdentifier,
+                preceded(tag("UNREFERENCED DUPLICATE "), consumed(identifier)),
+                |(text, id)| {
+                    (
+                        Data::DuplicateIdentifier(id),
+                        Fields::new().with(FieldKind::Identifier, text),
+                    )
+                },
             ),
-            map(specifier, Data::DuplicateSpecifier),
+            map(specifier, |(specifier, fields)| {
+                (Data::DuplicateSpecifier(specifier), fields)
+            }),
         )),
     )(input)
 }
 
-fn specifier<'a, E>(input: &'a str) -> IResult<&'a str, Specifier<&'a str>, E>
+fn specifier<'a, E>(
+    input: &'a str,
+) -> IResult<&'a str, Located<'a, Specifier<&'a str>>, E>
 where
-    E: ParseError<&'a str>,
+    E: ParseError<&'a str> + ContextError<&'a str>,
 {
     map(
         pair(
             terminated(
                 delimited(
                     char('('),
-                    separated_pair(r#type, char(','), scope),
+                    separated_pair(
+                        context("symbol type", consumed(r#type)),
+                        char(','),
+                        context("symbol scope", consumed(scope)),
+                    ),
                     char(')'),
                 ),
                 char(' '),
             ),
-            preceded(tag("found in "), origin),
+            preceded(tag("found in "), context("object file", origin)),
         ),
-        |((r#type, scope), origin)| Specifier {
-            r#type,
-            scope,
-            origin,
+        |(((type_text, r#type), (scope_text, scope)), origin)| {
+            let fields = Fields::new()
+                .with(FieldKind::Type, type_text)
+                .with(FieldKind::Scope, scope_text)
+                .with_origin(&origin);
+            (
+                Specifier {
+                    r#type,
+                    scope,
+                    origin,
+                },
+                fields,
+            )
         },
     )(input)
 }
 
-fn depth<'a, E>(input: &'a str) -> IResult<&'a str, u32, E>
+/// Parses the depth of a node, along with its digits.
+fn depth<'a, E>(input: &'a str) -> IResult<&'a str, (&'a str, u32), E>
 where
     E: ParseError<&'a str> + FromExternalError<&'a str, ParseIntError>,
 {
     map_res(
         delimited(take_while(|c| c == ' '), digit1, tag("] ")),
-        str::parse::<u32>,
+        |digits: &'a str| digits.parse::<u32>().map(|depth| (digits, depth)),
     )(input)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{node, title, Node, Specifier};
+    use super::{node_located, title, Node, Specifier};
     use crate::{
         map::{Origin, SectionName},
         utils::test_utils::assert_diff,
@@ -351,7 +555,10 @@ Link map of __start\r\n\
         ];
 
         let mut parser = terminated::<_, _, _, ErrorTree<&str>, _, _>(
-            alt((map(title, Line::TreeTitle), map(node, Line::TreeNode))),
+            alt((
+                map(title, Line::TreeTitle),
+                map(node_located, |(node, _)| Line::TreeNode(node)),
+            )),
             eof,
         );
 
@@ -0,0 +1,221 @@
+//! WebAssembly bindings, built with the `wasm` feature as a `cdylib`, e.g.
+//! with `cargo rustc --lib --release --target wasm32-unknown-unknown
+//! --features wasm --crate-type cdylib` followed by `wasm-bindgen`.
+//!
+//! Symbols and sections are returned as plain JS objects with camelCase
+//! keys, and absent values as `undefined`.
+
+use crate::{
+    demangle::demangle, map::SectionName, map_file::MapFile, memory_table,
+    section_table,
+};
+use serde::Serialize;
+use serde_wasm_bindgen::to_value;
+use wasm_bindgen::prelude::*;
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Symbol {
+    section: String,
+    name: String,
+    demangled: Option<String>,
+    addr: u32,
+    virt_addr: u32,
+    file_addr: Option<u32>,
+    /// Absent for entry symbols, which are part of their parent symbol.
+    size: Option<u32>,
+    align: Option<u8>,
+    parent: Option<String>,
+    object: String,
+    source: Option<String>,
+}
+
+impl Symbol {
+    fn new(
+        section: &SectionName<String>,
+        symbol: &section_table::Symbol<String>,
+    ) -> Self {
+        let (size, align, parent) = match &symbol.data {
+            section_table::Data::Parent { size, align } => {
+                (Some(*size), Some(*align), None)
+            }
+            section_table::Data::Child { parent } => {
+                (None, None, Some(parent.to_string()))
+            }
+        };
+        let name = symbol.id.to_string();
+
+        Self {
+            section: section.to_string(),
+            demangled: demangle(&name),
+            name,
+            addr: symbol.addr,
+            virt_addr: symbol.virt_addr,
+            file_addr: symbol.file_addr,
+            size,
+            align,
+            parent,
+            object: symbol.origin.obj.clone(),
+            source: symbol.origin.src.clone(),
+        }
+    }
+}
+
+/// A section layout, along with its row of the memory map if it has one.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Section {
+    name: String,
+    virt_addr: Option<u32>,
+    size: Option<u32>,
+    file_addr: Option<u32>,
+    symbols: Vec<Symbol>,
+}
+
+/// A parsed map.
+#[wasm_bindgen]
+pub struct Map(MapFile<String>);
+
+#[wasm_bindgen]
+impl Map {
+    /// Parses a whole map, throwing an error with a rendered report if it
+    /// cannot be parsed.
+    #[wasm_bindgen(constructor)]
+    pub fn new(text: &str) -> Result<Map, JsError> {
+        crate::parse_map(text)
+            .map(|map_file| Self(map_file.map_str(&mut str::to_owned)))
+            .map_err(|err| JsError::new(&err.report(text).to_string()))
+    }
+
+    /// Every section layout symbol, in order.
+    pub fn symbols(&self) -> Result<JsValue, JsError> {
+        Ok(to_value(&symbols(&self.0))?)
+    }
+
+    /// Every section layout along with its symbols.
+    pub fn sections(&self) -> Result<JsValue, JsError> {
+        Ok(to_value(&sections(&self.0))?)
+    }
+
+    /// Finds the symbol whose data contains `virt_addr`.
+    #[wasm_bindgen(js_name = symbolAt)]
+    pub fn symbol_at(&self, virt_addr: u32) -> Result<JsValue, JsError> {
+        let symbol = self
+            .0
+            .symbol_at(virt_addr)
+            .map(|(section, symbol)| Symbol::new(section, symbol));
+        Ok(to_value(&symbol)?)
+    }
+
+    /// Finds the symbols with the given name, which is matched against both
+    /// the mangled and the demangled name.
+    pub fn find(&self, name: &str) -> Result<JsValue, JsError> {
+        Ok(to_value(&find(&self.0, name))?)
+    }
+}
+
+/// Parses a whole map, like `new Map(text)`.
+#[wasm_bindgen]
+pub fn parse(text: &str) -> Result<Map, JsError> {
+    Map::new(text)
+}
+
+fn symbols(map_file: &MapFile<String>) -> Vec<Symbol> {
+    map_file
+        .symbols()
+        .map(|(section, symbol)| Symbol::new(section, symbol))
+        .collect()
+}
+
+fn sections(map_file: &MapFile<String>) -> Vec<Section> {
+    map_file
+        .sections
+        .iter()
+        .map(|section| {
+            let entry =
+                map_file.memory.iter().find_map(|entry| match &entry.data {
+                    memory_table::Data::Main { name, virt_addr }
+                        if name == &section.name =>
+                    {
+                        Some((*virt_addr, entry))
+                    }
+                    _ => None,
+                });
+
+            Section {
+                name: section.name.to_string(),
+                virt_addr: entry.map(|(virt_addr, _)| virt_addr),
+                size: entry.map(|(_, entry)| entry.size),
+                file_addr: entry.map(|(_, entry)| entry.file_addr),
+                symbols: section
+                    .symbols
+                    .iter()
+                    .map(|symbol| Symbol::new(&section.name, symbol))
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+fn find(map_file: &MapFile<String>, name: &str) -> Vec<Symbol> {
+    map_file
+        .symbols()
+        .filter(|(_, symbol)| {
+            let id = symbol.id.to_string();
+            id == name || demangle(&id).is_some_and(|d| d == name)
+        })
+        .map(|(section, symbol)| Symbol::new(section, symbol))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find, sections, Section, Symbol};
+    use crate::parse_map;
+
+    #[test]
+    fn test_wasm() {
+        let input = "\
+.text section layout\r\n\
+\x20 00000000 00002c 80003100  4 __dt__15CMemoryInStreamFv\x20\tCMemoryInStream.o \r\n\
+\r\n\
+Memory map:\r\n\
+\x20           .text  80003100 000023a8 000001c0\r\n\
+";
+        let map_file = parse_map(input).unwrap().map_str(&mut str::to_owned);
+
+        let symbol = Symbol {
+            section: ".text".to_owned(),
+            name: "__dt__15CMemoryInStreamFv".to_owned(),
+            demangled: Some("CMemoryInStream::~CMemoryInStream()".to_owned()),
+            addr: 0,
+            virt_addr: 0x80003100,
+            file_addr: None,
+            size: Some(0x2c),
+            align: Some(4),
+            parent: None,
+            object: "CMemoryInStream.o".to_owned(),
+            source: None,
+        };
+        assert_eq!(
+            find(&map_file, "CMemoryInStream::~CMemoryInStream()"),
+            vec![symbol]
+        );
+
+        let sections = sections(&map_file);
+        assert_eq!(sections.len(), 1);
+        let Section {
+            name,
+            virt_addr,
+            size,
+            file_addr,
+            symbols,
+        } = &sections[0];
+        assert_eq!(name, ".text");
+        assert_eq!(
+            (*virt_addr, *size, *file_addr),
+            (Some(0x80003100), Some(0x23a8), Some(0x1c0))
+        );
+        assert_eq!(symbols.len(), 1);
+    }
+}
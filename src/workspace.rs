@@ -0,0 +1,387 @@
+//! A symbol database over the maps of a main DOL and the REL modules it
+//! loads.
+//!
+//! A REL is linked separately from the DOL, so its map places each section at
+//! a module relative address and leaves the symbols it imports from the DOL,
+//! e.g. the ones referenced by `_unresolved`, undefined. A [`Workspace`] ties
+//! those maps together: it resolves the imports against the globals of the
+//! DOL and turns module relative locations into absolute addresses once the
+//! load addresses of the sections of the modules are known.
+
+use crate::{
+    map::{Identifier, SectionName},
+    map_file::MapFile,
+    memory_table, section_table, tree,
+};
+use std::{collections::HashSet, hash::Hash};
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+pub enum ModuleKind {
+    /// The main executable, whose map holds absolute addresses.
+    Dol,
+    /// A relocatable module, whose map holds module relative addresses.
+    Rel,
+}
+
+/// The map of a module, tagged with its module id.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Module<S: Eq + PartialEq> {
+    /// The module id, which is conventionally zero for the DOL.
+    pub id: u32,
+    pub kind: ModuleKind,
+    pub map_file: MapFile<S>,
+    /// The addresses that the sections of a REL were loaded at, e.g. from
+    /// the section table of the loaded REL. They cannot be derived from the
+    /// map, whose file offsets refer to the ELF that the REL was converted
+    /// from. Addresses given for the DOL take precedence over its map.
+    pub section_addrs: Vec<(SectionName<S>, u32)>,
+}
+
+impl<S: Eq + PartialEq> Module<S> {
+    pub fn new(id: u32, kind: ModuleKind, map_file: MapFile<S>) -> Self {
+        Self {
+            id,
+            kind,
+            map_file,
+            section_addrs: Vec::new(),
+        }
+    }
+
+    /// The address that the map places the start of a section at.
+    fn link_addr(&self, name: &SectionName<S>) -> u32 {
+        self.map_file
+            .memory
+            .iter()
+            .find_map(|entry| match &entry.data {
+                memory_table::Data::Main {
+                    name: entry_name,
+                    virt_addr,
+                } if entry_name == name => Some(*virt_addr),
+                _ => None,
+            })
+            .or_else(|| {
+                let section = self.map_file.section(name)?;
+                section.symbols.first().map(|symbol| symbol.virt_addr)
+            })
+            .unwrap_or_default()
+    }
+
+    /// The absolute address of a section, if it is known.
+    pub fn section_addr(&self, name: &SectionName<S>) -> Option<u32> {
+        if let Some((_, addr)) = self
+            .section_addrs
+            .iter()
+            .find(|(section, _)| section == name)
+        {
+            return Some(*addr);
+        }
+
+        match self.kind {
+            ModuleKind::Dol => Some(self.link_addr(name)),
+            ModuleKind::Rel => None,
+        }
+    }
+
+    fn locate<'a>(
+        &'a self,
+        section: &'a SectionName<S>,
+        symbol: &'a section_table::Symbol<S>,
+    ) -> Location<'a, S> {
+        let offset = symbol.virt_addr.wrapping_sub(self.link_addr(section));
+        Location {
+            module: self.id,
+            section,
+            symbol,
+            offset,
+            addr: self
+                .section_addr(section)
+                .map(|addr| addr.wrapping_add(offset)),
+        }
+    }
+}
+
+/// A section layout symbol, located as "module + section + offset" and, if
+/// the section's load address is known, as an absolute address.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct Location<'a, S: Eq + PartialEq> {
+    pub module: u32,
+    pub section: &'a SectionName<S>,
+    pub symbol: &'a section_table::Symbol<S>,
+    /// The offset of the symbol from the start of its section.
+    pub offset: u32,
+    pub addr: Option<u32>,
+}
+
+/// A symbol that a module references but does not define.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct Import<'a, S: Eq + PartialEq> {
+    pub id: &'a Identifier<S>,
+    /// The global definition in the DOL, if there is one.
+    pub target: Option<Location<'a, S>>,
+}
+
+/// The maps of every module of a program.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Workspace<S: Eq + PartialEq> {
+    modules: Vec<Module<S>>,
+}
+
+impl<S: Eq + PartialEq> Default for Workspace<S> {
+    fn default() -> Self {
+        Self {
+            modules: Vec::new(),
+        }
+    }
+}
+
+impl<S: Eq + PartialEq + Hash> Workspace<S> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a module, replacing any module with the same id.
+    pub fn add(&mut self, module: Module<S>) -> &mut Module<S> {
+        let index = match self.modules.iter().position(|m| m.id == module.id) {
+            Some(index) => {
+                self.modules[index] = module;
+                index
+            }
+            None => {
+                self.modules.push(module);
+                self.modules.len() - 1
+            }
+        };
+        &mut self.modules[index]
+    }
+
+    pub fn modules(&self) -> &[Module<S>] {
+        &self.modules
+    }
+
+    pub fn module(&self, id: u32) -> Option<&Module<S>> {
+        self.modules.iter().find(|module| module.id == id)
+    }
+
+    pub fn module_mut(&mut self, id: u32) -> Option<&mut Module<S>> {
+        self.modules.iter_mut().find(|module| module.id == id)
+    }
+
+    /// The DOL, which the imports of every REL are resolved against.
+    pub fn main(&self) -> Option<&Module<S>> {
+        self.modules
+            .iter()
+            .find(|module| module.kind == ModuleKind::Dol)
+    }
+
+    /// Finds the symbol whose data contains `offset` within a section of a
+    /// module.
+    pub fn symbol_at(
+        &self,
+        module: u32,
+        section: &SectionName<S>,
+        offset: u32,
+    ) -> Option<Location<'_, S>> {
+        let module = self.module(module)?;
+        let section = module.map_file.section(section)?;
+        let virt_addr = module.link_addr(&section.name).wrapping_add(offset);
+
+        section
+            .symbols
+            .iter()
+            .find(|symbol| symbol.contains(virt_addr))
+            .map(|symbol| module.locate(&section.name, symbol))
+    }
+
+    /// Finds the symbol whose data contains the absolute address `addr`,
+    /// across every module whose load addresses are known.
+    pub fn symbol_at_addr(&self, addr: u32) -> Option<Location<'_, S>> {
+        self.modules.iter().find_map(|module| {
+            module.map_file.sections.iter().find_map(|section| {
+                let start = module.section_addr(&section.name)?;
+                let offset = addr.checked_sub(start)?;
+                self.symbol_at(module.id, &section.name, offset)
+            })
+        })
+    }
+
+    /// Finds the definition of `id` as seen from a module: its own
+    /// definition if it has one, or else the global one in the DOL.
+    pub fn resolve(
+        &self,
+        module: u32,
+        id: &Identifier<S>,
+    ) -> Option<Location<'_, S>> {
+        let module = self.module(module)?;
+        module
+            .map_file
+            .symbols()
+            .find(|(_, symbol)| &symbol.id == id)
+            .map(|(section, symbol)| module.locate(section, symbol))
+            .or_else(|| self.resolve_global(id))
+    }
+
+    /// Finds the global definition of `id` in the DOL.
+    ///
+    /// Locals of the same name, e.g. static functions of other objects, are
+    /// skipped by only accepting a definition from an object whose closure
+    /// tree node is not local. Without any closure tree nodes for `id`, the
+    /// first definition is taken.
+    fn resolve_global(&self, id: &Identifier<S>) -> Option<Location<'_, S>> {
+        let main = self.main()?;
+
+        let specifiers = main
+            .map_file
+            .trees
+            .iter()
+            .flat_map(|tree| &tree.nodes)
+            .filter_map(|node| match &node.data {
+                tree::Data::Object(node_id, specifier) if node_id == id => {
+                    Some(specifier)
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        main.map_file
+            .symbols()
+            .find(|(_, symbol)| {
+                &symbol.id == id
+                    && (specifiers.is_empty()
+                        || specifiers.iter().any(|specifier| {
+                            specifier.scope != tree::Scope::Local
+                                && specifier.origin.obj == symbol.origin.obj
+                        }))
+            })
+            .map(|(section, symbol)| main.locate(section, symbol))
+    }
+
+    /// The symbols that the closure trees of a module reference but its
+    /// section layouts do not define, in order of first reference, along
+    /// with their definitions in the DOL.
+    pub fn imports(&self, module: u32) -> Vec<Import<'_, S>> {
+        let Some(module) = self.module(module) else {
+            return Vec::new();
+        };
+        let defined = module
+            .map_file
+            .symbols()
+            .map(|(_, symbol)| &symbol.id)
+            .collect::<HashSet<_>>();
+
+        let mut seen = HashSet::new();
+        module
+            .map_file
+            .trees
+            .iter()
+            .flat_map(|tree| &tree.nodes)
+            .filter_map(|node| match &node.data {
+                tree::Data::Object(id, _) => Some(id),
+                _ => None,
+            })
+            .filter(|id| !defined.contains(id) && seen.insert(*id))
+            .map(|id| Import {
+                id,
+                target: match module.kind {
+                    ModuleKind::Dol => None,
+                    ModuleKind::Rel => self.resolve_global(id),
+                },
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Module, ModuleKind, Workspace};
+    use crate::{map::SectionName, parse_map};
+
+    #[test]
+    fn test_workspace() {
+        let dol = "\
+Link map of __start\r\n\
+\x20 1] __start (func,global) found in __start.c.o \r\n\
+\x20  2] OSReport (func,global) found in os.a OSError.c\r\n\
+\x20  2] helper (func,local) found in __start.c.o \r\n\
+\x20  2] report (func,local) found in a.o \r\n\
+\x20  2] report (func,global) found in b.o \r\n\
+\r\n\
+.text section layout\r\n\
+\x20 00000000 000010 80003100  4 __start\x20\t__start.c.o \r\n\
+\x20 00000010 000020 80003110  4 OSReport\x20\tos.a OSError.c\r\n\
+\x20 00000030 000010 80003130  4 helper\x20\t__start.c.o \r\n\
+\x20 00000040 000010 80003140  4 report\x20\ta.o \r\n\
+\x20 00000050 000010 80003150  4 report\x20\tb.o \r\n\
+\r\n\
+Memory map:\r\n\
+\x20           .text  80003100 00000060 00000100\r\n\
+";
+        let rel = "\
+Link map of _prolog\r\n\
+\x20 1] _prolog (func,global) found in rel.o \r\n\
+\x20  2] OSReport (func,global) found in rel.o \r\n\
+\x20  2] helper (func,global) found in rel.o \r\n\
+\x20  2] report (func,global) found in rel.o \r\n\
+Link map of _unresolved\r\n\
+\x20 1] _unresolved (func,global) found in rel.o \r\n\
+\x20  2] OSReport (func,global) found in rel.o \r\n\
+\r\n\
+.text section layout\r\n\
+\x20 00000000 000008 00000000  4 _prolog\x20\trel.o \r\n\
+\x20 00000008 000008 00000008  4 _unresolved\x20\trel.o \r\n\
+\r\n\
+Memory map:\r\n\
+\x20           .text  00000000 00000010 00000040\r\n\
+";
+        let text = SectionName::Text;
+
+        let mut workspace = Workspace::new();
+        workspace.add(Module::new(
+            0,
+            ModuleKind::Dol,
+            parse_map(dol).unwrap(),
+        ));
+        workspace.add(Module::new(
+            1,
+            ModuleKind::Rel,
+            parse_map(rel).unwrap(),
+        ));
+
+        let imports = workspace.imports(1);
+        assert_eq!(imports.len(), 3);
+        assert_eq!(imports[0].id.to_string(), "OSReport");
+        let target = imports[0].target.unwrap();
+        assert_eq!((target.module, target.offset), (0, 0x10));
+        assert_eq!(target.addr, Some(0x80003110));
+        // Locals of the DOL cannot be linked against.
+        assert_eq!(imports[1].id.to_string(), "helper");
+        assert_eq!(imports[1].target, None);
+        // The global definition is picked over a static of the same name.
+        assert_eq!(imports[2].id.to_string(), "report");
+        assert_eq!(imports[2].target.unwrap().addr, Some(0x80003150));
+        assert_eq!(workspace.resolve(1, imports[0].id), Some(target));
+
+        let unresolved = workspace.symbol_at(1, &text, 0xc).unwrap();
+        assert_eq!(unresolved.symbol.id.to_string(), "_unresolved");
+        assert_eq!((unresolved.offset, unresolved.addr), (8, None));
+
+        // The REL was loaded at 0x80500000, and its section table placed
+        // `.text` after the 0x4c byte header and the three entries of the
+        // section table, unlike the ELF offset from the memory map.
+        workspace.module_mut(1).unwrap().section_addrs =
+            vec![(text, 0x80500064)];
+        let unresolved = workspace.symbol_at(1, &text, 0xc).unwrap();
+        assert_eq!(unresolved.addr, Some(0x8050006c));
+        assert_eq!(workspace.symbol_at_addr(0x80500070), Some(unresolved));
+        assert_eq!(workspace.symbol_at_addr(0x80500040), None);
+        assert_eq!(
+            workspace
+                .symbol_at_addr(0x80003134)
+                .unwrap()
+                .symbol
+                .id
+                .to_string(),
+            "helper"
+        );
+        assert_eq!(workspace.symbol_at_addr(0x80600000), None);
+    }
+}